@@ -7,9 +7,9 @@ use fred::interfaces::SortedSetsInterface;
 /// to be half has much as they were before.
 async fn rescale_viewed(client: &RedisClient) -> Result<(), RedisError> {
     loop {
-        client.zremrangebyrank("viewed:", 20000, -1).await?;
+        let () = client.zremrangebyrank("viewed:", 20000, -1).await?;
         // Store it in itself, to rescale in half
-        client.zinterstore("viewed:", "viewed:", 0.5, None).await?;
+        let () = client.zinterstore("viewed:", "viewed:", 0.5, None).await?;
         tokio::time::sleep(std::time::Duration::from_secs(300)).await;
     }
 }
@@ -8,10 +8,17 @@ use fred::types::RedisConfig;
 
 pub mod analytics;
 pub mod database_rows_cache;
+pub mod session_cleaner;
 pub mod session_cookie;
+pub mod session_store;
 pub mod shopping_cart;
 pub mod web_page_caching;
 
+pub use session_cleaner::{
+    CleanerMetrics, CleanupPolicy, CleanupReport, SessionCleaner,
+};
+pub use session_store::SessionStore;
+
 pub fn get_sys_time_in_secs() -> u64 {
     match SystemTime::now().duration_since(SystemTime::UNIX_EPOCH) {
         Ok(n) => n.as_secs(),
@@ -25,6 +32,6 @@ pub async fn init_redis_client() -> RedisClient {
     )
     .unwrap();
     let client = RedisClient::new(config, None, None, None);
-    let _ = client.init().await.unwrap();
+    let _connection = client.init().await.unwrap();
     client
 }
@@ -1,16 +1,18 @@
-use std::collections::HashMap;
-use std::time::SystemTime;
-
-use fred::clients::RedisClient;
-use fred::error::RedisError;
-use fred::interfaces::{
-    ClientLike, HashesInterface, KeysInterface, SortedSetsInterface,
-};
-use fred::types::RedisConfig;
-
-use fake_web_retailer::init_redis_client;
+use fake_web_retailer::{init_redis_client, CleanupPolicy, SessionStore};
+use tokio_util::sync::CancellationToken;
 
 #[tokio::main(flavor = "multi_thread")]
 async fn main() {
-    let client = init_redis_client().await;
+    let sessions = SessionStore::new(init_redis_client().await);
+
+    // Clean sessions in background until Ctrl-C
+    let cancel = CancellationToken::new();
+    let cleaner = sessions.cleaner(CleanupPolicy::default());
+    let metrics = cleaner.clone();
+    let task = cleaner.spawn(cancel.clone());
+
+    tokio::signal::ctrl_c().await.unwrap();
+    cancel.cancel();
+    task.await.unwrap();
+    println!("{:?}", metrics.metrics());
 }
@@ -0,0 +1,220 @@
+use std::fmt;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use fred::clients::RedisClient;
+use fred::error::RedisError;
+use fred::interfaces::LuaInterface;
+use tokio::task::JoinHandle;
+use tokio_util::sync::CancellationToken;
+
+use crate::get_sys_time_in_secs;
+
+/// Which sessions are removed by the `SessionCleaner`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CleanupPolicy {
+    /// Keep at most this many sessions, the least recently seen
+    /// are removed first. `None` disables the limit.
+    pub max_sessions: Option<u64>,
+    /// Remove sessions not seen for this long. `None` disables expiry.
+    pub max_idle: Option<Duration>,
+    /// Sessions removed by one script call, bounds how long redis
+    /// is blocked by the cleaner.
+    pub batch_size: usize,
+    /// Pause between cleanup runs.
+    pub interval: Duration,
+}
+
+impl Default for CleanupPolicy {
+    /// 10 million sessions, idle for at most 30 days, cleaned every
+    /// minute in batches of 100.
+    fn default() -> Self {
+        CleanupPolicy {
+            max_sessions: Some(10_000_000),
+            max_idle: Some(Duration::from_secs(30 * 24 * 60 * 60)),
+            batch_size: 100,
+            interval: Duration::from_secs(60),
+        }
+    }
+}
+
+/// Result of one cleanup run.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct CleanupReport {
+    /// Sessions removed because they were idle for too long.
+    pub expired: u64,
+    /// Sessions removed because there were too many sessions.
+    pub evicted: u64,
+    /// Number of script calls.
+    pub batches: u64,
+    pub elapsed: Duration,
+}
+
+impl CleanupReport {
+    pub fn removed(&self) -> u64 {
+        self.expired + self.evicted
+    }
+}
+
+impl fmt::Display for CleanupReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "removed {} sessions ({} expired, {} evicted) in {} batches, {:?}",
+            self.removed(),
+            self.expired,
+            self.evicted,
+            self.batches,
+            self.elapsed
+        )
+    }
+}
+
+/// Totals of all runs of the cleaner, since it was created.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct CleanerMetrics {
+    pub runs: u64,
+    pub expired: u64,
+    pub evicted: u64,
+    pub last_run: Option<CleanupReport>,
+}
+
+/// Long-running task removing sessions by `CleanupPolicy`: the `login:`
+/// entry, the `recent:` entry, `history:{token}`, `cart:{token}` and
+/// the legacy `viewed:{token}` list.
+///
+/// Each batch is selected and removed by one script, so several cleaners
+/// can run against the same redis without removing a session twice.
+/// Clones share metrics.
+#[derive(Clone)]
+pub struct SessionCleaner {
+    client: RedisClient,
+    policy: CleanupPolicy,
+    metrics: Arc<Mutex<CleanerMetrics>>,
+}
+
+impl SessionCleaner {
+    pub fn new(client: RedisClient, policy: CleanupPolicy) -> Self {
+        SessionCleaner {
+            client,
+            policy,
+            metrics: Arc::default(),
+        }
+    }
+
+    /// Remove all sessions which violate the policy now.
+    pub async fn run_once(&self) -> Result<CleanupReport, RedisError> {
+        self.clean(&CancellationToken::new()).await
+    }
+
+    /// Run cleanup every `interval` until the token is cancelled.
+    /// Cancellation is checked between batches, so the current batch
+    /// is always finished. A failed run is logged and retried on the
+    /// next interval.
+    pub async fn run(&self, cancel: CancellationToken) {
+        while !cancel.is_cancelled() {
+            if let Err(e) = self.clean(&cancel).await {
+                eprintln!("Session cleanup failed: {e}");
+            }
+            tokio::select! {
+                _ = cancel.cancelled() => break,
+                _ = tokio::time::sleep(self.policy.interval) => {}
+            }
+        }
+    }
+
+    /// Spawn `run` in background.
+    pub fn spawn(self, cancel: CancellationToken) -> JoinHandle<()> {
+        tokio::spawn(async move { self.run(cancel).await })
+    }
+
+    pub fn metrics(&self) -> CleanerMetrics {
+        self.metrics.lock().unwrap().clone()
+    }
+
+    async fn clean(
+        &self,
+        cancel: &CancellationToken,
+    ) -> Result<CleanupReport, RedisError> {
+        let start = Instant::now();
+        let mut report = CleanupReport::default();
+        // Empty cutoff disables expiry, negative maximum disables the limit
+        let idle_cutoff = match self.policy.max_idle {
+            Some(max_idle) => get_sys_time_in_secs()
+                .saturating_sub(max_idle.as_secs())
+                .to_string(),
+            None => String::new(),
+        };
+        let max_sessions = self
+            .policy
+            .max_sessions
+            .map_or(-1, |max| max.min(i64::MAX as u64) as i64);
+        let batch_size = self.policy.batch_size.max(1);
+
+        while !cancel.is_cancelled() {
+            let (expired, evicted): (u64, u64) = self
+                .client
+                .eval(
+                    CLEAN_SESSIONS_LUA,
+                    vec!["recent:", "login:"],
+                    vec![
+                        idle_cutoff.clone(),
+                        max_sessions.to_string(),
+                        batch_size.to_string(),
+                    ],
+                )
+                .await?;
+            report.batches += 1;
+            report.expired += expired;
+            report.evicted += evicted;
+            if ((expired + evicted) as usize) < batch_size {
+                break;
+            }
+        }
+        report.elapsed = start.elapsed();
+
+        let mut metrics = self.metrics.lock().unwrap();
+        metrics.runs += 1;
+        metrics.expired += report.expired;
+        metrics.evicted += report.evicted;
+        metrics.last_run = Some(report.clone());
+        Ok(report)
+    }
+}
+
+/// Remove up to `ARGV[3]` sessions from the `KEYS[1]` zset of last seen
+/// timestamps and the `KEYS[2]` hash of logins: first the sessions seen
+/// before `ARGV[1]` (if not empty), then the oldest sessions above
+/// `ARGV[2]` total (if not negative).
+/// Returns numbers of expired and evicted sessions.
+const CLEAN_SESSIONS_LUA: &str = r#"
+local batch = tonumber(ARGV[3])
+local tokens = {}
+if ARGV[1] ~= '' then
+    tokens = redis.call('ZRANGEBYSCORE', KEYS[1],
+        '-inf', '(' .. ARGV[1], 'LIMIT', 0, batch)
+end
+local expired = #tokens
+local evicted = 0
+local max = tonumber(ARGV[2])
+if max >= 0 and expired < batch then
+    local excess = redis.call('ZCARD', KEYS[1]) - expired - max
+    if excess > 0 then
+        -- Expired sessions are the oldest ones, take the next by rank
+        local count = math.min(excess, batch - expired)
+        local oldest = redis.call('ZRANGE', KEYS[1],
+            expired, expired + count - 1)
+        for _, token in ipairs(oldest) do
+            table.insert(tokens, token)
+        end
+        evicted = #oldest
+    end
+end
+for _, token in ipairs(tokens) do
+    redis.call('DEL', 'history:' .. token, 'viewed:' .. token,
+        'cart:' .. token)
+    redis.call('HDEL', KEYS[2], token)
+    redis.call('ZREM', KEYS[1], token)
+end
+return {expired, evicted}
+"#;
@@ -1,36 +1,53 @@
+use std::time::SystemTime;
+
 use fred::clients::RedisClient;
 use fred::error::RedisError;
 use fred::interfaces::{
-    HashesInterface, KeysInterface, ListInterface, SortedSetsInterface,
+    HashesInterface, SortedSetsInterface, TransactionInterface,
 };
 
 use crate::get_sys_time_in_secs;
 
-async fn check_token(
+/// Default number of items kept in the `history:{token}` zset.
+pub const RECENTLY_VIEWED_LIMIT: usize = 25;
+
+/// Zset of items recently viewed within the session, scored by the view
+/// timestamp. It replaced the `viewed:{token}` list, so the name differs
+/// and old lists are only dropped together with their sessions.
+pub fn history_key(token: &str) -> String {
+    format!("history:{token}")
+}
+
+/// Find the user logged in with the token, `None` if there is no such
+/// session.
+pub async fn check_token(
     client: &RedisClient,
     token: &str,
-) -> Result<String, RedisError> {
+) -> Result<Option<String>, RedisError> {
     client.hget("login:", token).await
 }
 
 /// If user perform any request, we should update user's token,
 /// this functions updates token-was-used request timestamp to `now`
 /// and if user viewed some item, we store that item in
-/// `viewed:{uuid_user_token}` list.
-/// We keep that zset size within bound of 0..25
-async fn update_token(
+/// `history:{uuid_user_token}` zset, scored by the view timestamp.
+/// Viewing the same item again only moves it to the top, and we keep
+/// that zset size within bound of 0..`viewed_limit`. Commands are sent
+/// in a transaction, so concurrent requests never exceed the bound.
+pub async fn update_token(
     client: RedisClient, // Use owned value for benchmark
     token: &str,
     user: &str,
     item: Option<&str>,
+    viewed_limit: usize,
 ) -> Result<(), RedisError> {
     // Get the timestamp.
     let timestamp = get_sys_time_in_secs();
-    let pipe = client.pipeline();
+    let trx = client.multi();
     // Keep a mapping from the token to the logged-in user.
-    pipe.hset("login:", vec![(token, user)]).await?;
+    trx.hset::<(), _, _>("login:", vec![(token, user)]).await?;
     // Record when the token was last seen.
-    pipe.zadd(
+    trx.zadd::<(), _, _>(
         "recent:",
         None,
         None,
@@ -41,68 +58,48 @@ async fn update_token(
     .await?;
     if let Some(item) = item {
         // Record that the user viewed the item.
-        let recently_viewed_items = format!("viewed:{}", token);
-        pipe.lpush(&recently_viewed_items, item).await?;
-        // Remove old items, keeping the most recent 25.
-        pipe.lrange(recently_viewed_items, 0, 26).await?;
+        let viewed_at = view_timestamp(timestamp);
+        let recently_viewed_items = history_key(token);
+        trx.zadd::<(), _, _>(
+            &recently_viewed_items,
+            None,
+            None,
+            false,
+            false,
+            vec![(viewed_at, item)],
+        )
+        .await?;
+        // Remove old items, keeping the most recent `viewed_limit`.
+        trx.zremrangebyrank::<(), _>(
+            recently_viewed_items,
+            0,
+            -(viewed_limit as i64) - 1,
+        )
+        .await?;
         // With this one line added, we now have a record of all of the items that are viewed.
         // Even more useful, that list of items is ordered by the number of times that people
         // have seen the items, with the most-viewed item having the lowest score, and thus having an index of 0.
-        pipe.zincrby("viewed:", -1.0, item).await?;
+        trx.zincrby::<(), _, _>("viewed:", -1.0, item).await?;
     }
-    pipe.all().await?;
+    trx.exec::<()>(true).await?;
     Ok(())
 }
 
-/// This task should run every `some_amount` period of time in background.
-/// We’ll only keep the most recent 10 million sessions, so we track sessions
-/// in the `recent:` zset. If there are more sessions than in `LIMIT`, we delete
-/// first N sessions (oldest) fron that zset, remove them from
-/// `viewed:{uuid_session_token}` zset, and from `login:` HASH.
-async fn clean_sessions_task(client: &RedisClient) -> Result<(), RedisError> {
-    static LIMIT: i64 = 10000000;
-
-    loop {
-        // Find out how many tokens are known (cardinality).
-        let size: i64 = client.zcard("recent:").await?;
-        if size <= LIMIT {
-            return Ok(());
-        }
-
-        // Fetch the token IDs that should be removed.
-        let end_index = (size - LIMIT).min(100);
-        let tokens: Vec<String> = client
-            .zrange(
-                "recent:",
-                0,
-                end_index - 1,
-                /* default sort */ None,
-                /* not reversed, asc */ false,
-                /* not related to rank, only to score */ None,
-                /* without scores */ false,
-            )
-            .await?;
-
-        // Prepare the key names for the tokens to delete.
-        let mut session_keys = Vec::new();
-        for token in tokens.iter() {
-            // We will remove data from viewed zset
-            session_keys.push(format!("viewed:{}", token));
-            // We will remove items from shopping cart
-            session_keys.push(format!("cart:{}", token));
-        }
-
-        // Remove the oldest tokens.
-        let () = client.del(session_keys).await?;
-        let () = client.hdel("login:", tokens.clone()).await?;
-        let () = client.zrem("recent:", tokens).await?;
-    }
+/// Timestamp of an item view, with millisecond precision to keep
+/// the order of views within the same second. Falls back to `timestamp`.
+pub(crate) fn view_timestamp(timestamp: u64) -> f64 {
+    SystemTime::now()
+        .duration_since(SystemTime::UNIX_EPOCH)
+        .map(|time| time.as_millis() as f64 / 1000.)
+        .unwrap_or(timestamp as f64)
 }
 
 #[cfg(test)]
 mod benchmark {
     use std::{future::Future, pin::Pin};
 
+    use fred::interfaces::ListInterface;
+
     use super::*;
     use crate::init_redis_client;
 
@@ -123,8 +120,8 @@ mod benchmark {
         item: Option<&str>,
     ) -> Result<(), RedisError> {
         let timestamp = get_sys_time_in_secs();
-        client.hset("login:", vec![(token, user)]).await?;
-        client
+        let () = client.hset("login:", vec![(token, user)]).await?;
+        let () = client
             .zadd(
                 "recent:",
                 None,
@@ -136,9 +133,10 @@ mod benchmark {
             .await?;
         if let Some(item) = item {
             let recently_viewed_items = format!("viewed:{}", token);
-            client.lpush(&recently_viewed_items, item).await?;
-            client.lrange(recently_viewed_items, 0, 26).await?;
-            client.zincrby("viewed:", -1.0, item).await?;
+            let () = client.lpush(&recently_viewed_items, item).await?;
+            let _: Vec<String> =
+                client.lrange(recently_viewed_items, 0, 26).await?;
+            let () = client.zincrby("viewed:", -1.0, item).await?;
         }
         Ok(())
     }
@@ -165,7 +163,9 @@ mod benchmark {
         // 0: count: 25297, delta: 5, count/delta: 5059
         // 1: count: 11335, delta: 5, count/delta: 2267
         for (i, f) in vec![
-            force_boxed(update_token),
+            force_boxed(|c, t, u, i| {
+                update_token(c, t, u, i, RECENTLY_VIEWED_LIMIT)
+            }),
             force_boxed(update_token_old_version),
         ]
         .into_iter()
@@ -176,7 +176,12 @@ mod benchmark {
             let end = start + duration;
             while get_sys_time_in_secs() < end {
                 count += 1;
-                let _ = f(client.clone(), "token", "user", Some("item")).await;
+                // Versions store history in different types of keys,
+                // so each one uses its own session
+                let token = ["token-zset", "token-list"][i];
+                f(client.clone(), token, "user", Some("item"))
+                    .await
+                    .unwrap();
             }
             let delta = get_sys_time_in_secs() - start;
             println!(
@@ -0,0 +1,163 @@
+use std::collections::HashMap;
+
+use fred::clients::RedisClient;
+use fred::error::RedisError;
+use fred::interfaces::{
+    HashesInterface, KeysInterface, LuaInterface, SortedSetsInterface,
+};
+
+use crate::get_sys_time_in_secs;
+use crate::session_cleaner::{CleanupPolicy, SessionCleaner};
+use crate::session_cookie::{
+    check_token, history_key, update_token, view_timestamp,
+    RECENTLY_VIEWED_LIMIT,
+};
+use crate::shopping_cart::add_to_cart;
+
+/// Entry point for the web tier: login sessions, recently viewed items
+/// and shopping carts, all keyed by the session token.
+#[derive(Clone)]
+pub struct SessionStore {
+    client: RedisClient,
+    /// Number of items kept in the recently viewed history of a session.
+    viewed_limit: usize,
+}
+
+impl SessionStore {
+    /// Create a new store on top of an already initialized client,
+    /// keeping 25 recently viewed items per session.
+    pub fn new(client: RedisClient) -> Self {
+        SessionStore {
+            client,
+            viewed_limit: RECENTLY_VIEWED_LIMIT,
+        }
+    }
+
+    /// Replace the number of items kept in the recently viewed history.
+    pub fn with_viewed_limit(mut self, limit: usize) -> Self {
+        self.viewed_limit = limit;
+        self
+    }
+
+    /// Start the session of `user` with the token.
+    pub async fn login(
+        &self,
+        token: &str,
+        user: &str,
+    ) -> Result<(), RedisError> {
+        update_token(self.client.clone(), token, user, None, self.viewed_limit)
+            .await
+    }
+
+    /// Record a request within the session, optionally viewing the `item`,
+    /// the same way as `update_token` does. Session is checked by the same
+    /// script, so a session removed by the cleaner is never recreated.
+    /// Returns `false` if there is no session with the token.
+    pub async fn touch(
+        &self,
+        token: &str,
+        item: Option<&str>,
+    ) -> Result<bool, RedisError> {
+        let timestamp = get_sys_time_in_secs();
+        let mut args = vec![
+            token.to_string(),
+            timestamp.to_string(),
+            view_timestamp(timestamp).to_string(),
+            self.viewed_limit.to_string(),
+        ];
+        if let Some(item) = item {
+            args.push(item.to_string());
+        }
+        self.client
+            .eval(
+                TOUCH_LUA,
+                vec![
+                    "login:".to_string(),
+                    "recent:".to_string(),
+                    history_key(token),
+                    "viewed:".to_string(),
+                ],
+                args,
+            )
+            .await
+    }
+
+    /// End the session, dropping its recently viewed items and the cart.
+    pub async fn logout(&self, token: &str) -> Result<(), RedisError> {
+        let pipe = self.client.pipeline();
+        pipe.hdel::<(), _, _>("login:", token).await?;
+        pipe.zrem::<(), _, _>("recent:", token).await?;
+        pipe.del::<(), _>(vec![
+            history_key(token),
+            format!("viewed:{token}"),
+            format!("cart:{token}"),
+        ])
+        .await?;
+        pipe.all::<()>().await
+    }
+
+    /// User logged in with the token, `None` if there is no such session.
+    pub async fn get_user(
+        &self,
+        token: &str,
+    ) -> Result<Option<String>, RedisError> {
+        check_token(&self.client, token).await
+    }
+
+    /// Items viewed within the session, the most recent first.
+    pub async fn recently_viewed(
+        &self,
+        token: &str,
+    ) -> Result<Vec<String>, RedisError> {
+        self.client
+            .zrange(history_key(token), 0, -1, None, true, None, false)
+            .await
+    }
+
+    /// Set `count` of the item in the session cart, zero count removes it.
+    pub async fn add_to_cart(
+        &self,
+        token: &str,
+        item: &str,
+        count: u64,
+    ) -> Result<(), RedisError> {
+        add_to_cart(&self.client, token, item, count).await
+    }
+
+    /// Items in the session cart with their counts.
+    pub async fn get_cart(
+        &self,
+        token: &str,
+    ) -> Result<HashMap<String, u64>, RedisError> {
+        self.client.hgetall(format!("cart:{token}")).await
+    }
+
+    /// Cleaner removing sessions of this store by the policy.
+    pub fn cleaner(&self, policy: CleanupPolicy) -> SessionCleaner {
+        SessionCleaner::new(self.client.clone(), policy)
+    }
+
+    /// Underlying redis client.
+    pub fn client(&self) -> &RedisClient {
+        &self.client
+    }
+}
+
+/// Record a request within session `ARGV[1]` if it is in the `KEYS[1]`
+/// hash of logins: set its last seen time in the `KEYS[2]` zset to
+/// `ARGV[2]`, and if item `ARGV[5]` is given, add it to the `KEYS[3]`
+/// history with `ARGV[3]` view time, keep `ARGV[4]` most recent items
+/// and count the view in the `KEYS[4]` zset.
+/// Returns 0 if there is no such session.
+const TOUCH_LUA: &str = r#"
+if redis.call('HEXISTS', KEYS[1], ARGV[1]) == 0 then
+    return 0
+end
+redis.call('ZADD', KEYS[2], ARGV[2], ARGV[1])
+if ARGV[5] then
+    redis.call('ZADD', KEYS[3], ARGV[3], ARGV[5])
+    redis.call('ZREMRANGEBYRANK', KEYS[3], 0, -tonumber(ARGV[4]) - 1)
+    redis.call('ZINCRBY', KEYS[4], -1, ARGV[5])
+end
+return 1
+"#;
@@ -2,17 +2,19 @@ use fred::clients::RedisClient;
 use fred::error::RedisError;
 use fred::interfaces::HashesInterface;
 
-async fn add_to_cart(
+/// Set `count` of the `item` in the `cart:{session_token}` hash,
+/// zero count removes the item from the cart.
+pub async fn add_to_cart(
     client: &RedisClient,
     session_token: &str,
     item: &str,
     count: u64,
 ) -> Result<(), RedisError> {
     let key = format!("cart:{}", session_token);
-    if count <= 0 {
-        client.hdel(key, item).await?;
+    if count == 0 {
+        let () = client.hdel(key, item).await?;
     } else {
-        client.hset(key, (item, count)).await?;
+        let () = client.hset(key, (item, count)).await?;
     }
     Ok(())
 }
@@ -21,20 +21,21 @@ where
         return Ok(callback(&request));
     }
     let page_key = format!("cache:{}", hash_request(&request));
-    let content: Option<String> = Some(client.get(&page_key).await?);
-    if let None = content {
-        let content = callback(&request);
-        let () = client
-            .set(
-                &page_key,
-                content,
-                Some(fred::types::Expiration::EX(300)),
-                None,
-                false,
-            )
-            .await?;
+    let content: Option<String> = client.get(&page_key).await?;
+    if let Some(content) = content {
+        return Ok(content);
     }
-    return Ok(content.unwrap());
+    let content = callback(&request);
+    let () = client
+        .set(
+            &page_key,
+            content.clone(),
+            Some(fred::types::Expiration::EX(300)),
+            None,
+            false,
+        )
+        .await?;
+    Ok(content)
 }
 
 async fn should_cache(
@@ -42,9 +43,9 @@ async fn should_cache(
     request: &Request,
 ) -> Result<bool, RedisError> {
     // Get the item ID for the page, if any.
-    let item_id = extract_item_id(&request);
+    let item_id = extract_item_id(request);
     // Check whether the page can be statically cached and whether this is an item page.
-    if item_id.is_none() || is_dynamic_page(&request) {
+    if item_id.is_none() || is_dynamic_page(request) {
         return Ok(false);
     }
     // Get the rank of the item, from shopping_cart module
@@ -58,11 +59,11 @@ async fn should_cache(
     }
 }
 
-fn extract_item_id(req: &Request) -> Option<ItemId> {
+fn extract_item_id(_req: &Request) -> Option<ItemId> {
     Some(1)
 }
 
-fn is_dynamic_page(req: &Request) -> bool {
+fn is_dynamic_page(_req: &Request) -> bool {
     false
 }
 
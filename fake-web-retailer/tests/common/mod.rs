@@ -0,0 +1,12 @@
+//! Helpers shared by the integration tests.
+
+use fake_web_retailer::get_sys_time_in_secs;
+
+/// Name unique for the test run, so tests don't see each other's data.
+pub fn unique(name: &str) -> String {
+    let nanos = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap()
+        .subsec_nanos();
+    format!("{name}-{}-{nanos}", get_sys_time_in_secs())
+}
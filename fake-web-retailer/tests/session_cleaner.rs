@@ -0,0 +1,125 @@
+mod common;
+
+use std::time::Duration;
+
+use fake_web_retailer::{
+    get_sys_time_in_secs, init_redis_client, CleanupPolicy, SessionStore,
+};
+use fred::interfaces::SortedSetsInterface;
+use tokio::sync::Mutex;
+use tokio_util::sync::CancellationToken;
+
+use common::unique;
+
+/// Cleaners sweep all stale sessions, so tests in this file take turns.
+static SERIAL: Mutex<()> = Mutex::const_new(());
+
+/// Only expire sessions idle for an hour, so sessions of other tests
+/// are kept.
+fn idle_policy() -> CleanupPolicy {
+    CleanupPolicy {
+        max_sessions: None,
+        max_idle: Some(Duration::from_secs(60 * 60)),
+        batch_size: 10,
+        interval: Duration::from_millis(50),
+    }
+}
+
+/// Log in and pretend the session was last seen two hours ago.
+async fn stale_session(store: &SessionStore, token: &str) {
+    store.login(token, "user").await.unwrap();
+    store.touch(token, Some("item")).await.unwrap();
+    store.add_to_cart(token, "item", 1).await.unwrap();
+    let seen = get_sys_time_in_secs() - 2 * 60 * 60;
+    let () = store
+        .client()
+        .zadd("recent:", None, None, false, false, (seen as f64, token))
+        .await
+        .unwrap();
+}
+
+#[tokio::test]
+async fn idle_sessions_are_expired() {
+    let _serial = SERIAL.lock().await;
+    let store = SessionStore::new(init_redis_client().await);
+    let stale = unique("stale");
+    let fresh = unique("fresh");
+    stale_session(&store, &stale).await;
+    store.login(&fresh, "user").await.unwrap();
+
+    let cleaner = store.cleaner(idle_policy());
+    let report = cleaner.run_once().await.unwrap();
+    assert!(report.expired >= 1);
+    assert_eq!(report.evicted, 0);
+
+    assert_eq!(store.get_user(&stale).await.unwrap(), None);
+    assert!(store.recently_viewed(&stale).await.unwrap().is_empty());
+    assert!(store.get_cart(&stale).await.unwrap().is_empty());
+    assert!(store.get_user(&fresh).await.unwrap().is_some());
+
+    let metrics = cleaner.metrics();
+    assert_eq!(metrics.runs, 1);
+    assert_eq!(metrics.last_run, Some(report));
+    store.logout(&fresh).await.unwrap();
+}
+
+#[tokio::test]
+async fn concurrent_cleaners_remove_each_session_once() {
+    let _serial = SERIAL.lock().await;
+    let store = SessionStore::new(init_redis_client().await);
+    let tokens = (0..35).map(|_| unique("stale")).collect::<Vec<_>>();
+    for token in tokens.iter() {
+        stale_session(&store, token).await;
+    }
+
+    // Sessions of earlier runs may be stale too, count all of them.
+    // Last seen times are whole seconds, so this is `(cutoff`.
+    let cutoff = get_sys_time_in_secs() - 60 * 60;
+    let stale: u64 = store
+        .client()
+        .zcount("recent:", f64::NEG_INFINITY, cutoff as f64 - 0.5)
+        .await
+        .unwrap();
+    assert!(stale >= tokens.len() as u64);
+
+    let cleaners = (0..4)
+        .map(|_| store.cleaner(idle_policy()))
+        .collect::<Vec<_>>();
+    let runs = cleaners
+        .iter()
+        .cloned()
+        .map(|cleaner| tokio::spawn(async move { cleaner.run_once().await }))
+        .collect::<Vec<_>>();
+    let mut expired = 0;
+    for run in runs {
+        expired += run.await.unwrap().unwrap().expired;
+    }
+
+    // Each stale session is removed by exactly one of the cleaners
+    assert_eq!(expired, stale);
+    for token in tokens.iter() {
+        assert_eq!(store.get_user(token).await.unwrap(), None);
+    }
+}
+
+#[tokio::test]
+async fn cleaner_stops_on_cancellation() {
+    let _serial = SERIAL.lock().await;
+    let store = SessionStore::new(init_redis_client().await);
+    let stale = unique("stale");
+    let cleaner = store.cleaner(idle_policy());
+    let cancel = CancellationToken::new();
+    let task = cleaner.clone().spawn(cancel.clone());
+
+    // Session which went stale while the cleaner was running
+    stale_session(&store, &stale).await;
+    tokio::time::sleep(Duration::from_millis(200)).await;
+    assert_eq!(store.get_user(&stale).await.unwrap(), None);
+
+    cancel.cancel();
+    tokio::time::timeout(Duration::from_secs(1), task)
+        .await
+        .expect("cleaner is stopped")
+        .unwrap();
+    assert!(cleaner.metrics().runs >= 2);
+}
@@ -0,0 +1,106 @@
+mod common;
+
+use std::collections::HashMap;
+
+use fake_web_retailer::{init_redis_client, SessionStore};
+
+use common::unique;
+
+async fn store() -> SessionStore {
+    SessionStore::new(init_redis_client().await)
+}
+
+#[tokio::test]
+async fn session_lives_from_login_to_logout() {
+    let store = store().await;
+    let token = unique("token");
+    assert!(!store.touch(&token, Some("item")).await.unwrap());
+
+    store.login(&token, "alice").await.unwrap();
+    assert_eq!(
+        store.get_user(&token).await.unwrap().as_deref(),
+        Some("alice")
+    );
+    assert!(store.touch(&token, Some("first")).await.unwrap());
+    assert!(store.touch(&token, Some("second")).await.unwrap());
+    assert_eq!(
+        store.recently_viewed(&token).await.unwrap(),
+        vec!["second".to_string(), "first".to_string()]
+    );
+
+    store.add_to_cart(&token, "first", 2).await.unwrap();
+    store.add_to_cart(&token, "second", 1).await.unwrap();
+    store.add_to_cart(&token, "second", 0).await.unwrap();
+    assert_eq!(
+        store.get_cart(&token).await.unwrap(),
+        HashMap::from([("first".to_string(), 2)])
+    );
+
+    store.logout(&token).await.unwrap();
+    assert_eq!(store.get_user(&token).await.unwrap(), None);
+    assert!(store.recently_viewed(&token).await.unwrap().is_empty());
+    assert!(store.get_cart(&token).await.unwrap().is_empty());
+
+    // Ended session is not recreated by a late request
+    assert!(!store.touch(&token, Some("third")).await.unwrap());
+    assert_eq!(store.get_user(&token).await.unwrap(), None);
+    assert!(store.recently_viewed(&token).await.unwrap().is_empty());
+}
+
+#[tokio::test]
+async fn recently_viewed_items_are_bounded() {
+    let store = store().await;
+    let token = unique("token");
+    store.login(&token, "bob").await.unwrap();
+    for i in 0..30 {
+        store
+            .touch(&token, Some(&format!("item-{i}")))
+            .await
+            .unwrap();
+    }
+    let viewed = store.recently_viewed(&token).await.unwrap();
+    assert_eq!(viewed.len(), 25);
+    assert_eq!(viewed[0], "item-29");
+    store.logout(&token).await.unwrap();
+}
+
+#[tokio::test]
+async fn viewing_item_again_moves_it_to_the_top() {
+    let store = store().await.with_viewed_limit(3);
+    let token = unique("token");
+    store.login(&token, "carol").await.unwrap();
+    for item in ["a", "b", "c", "a", "d"] {
+        store.touch(&token, Some(item)).await.unwrap();
+    }
+    assert_eq!(
+        store.recently_viewed(&token).await.unwrap(),
+        vec!["d".to_string(), "a".to_string(), "c".to_string()]
+    );
+    store.logout(&token).await.unwrap();
+}
+
+#[tokio::test]
+async fn concurrent_views_keep_history_bounded() {
+    let store = store().await.with_viewed_limit(10);
+    let token = unique("token");
+    store.login(&token, "dave").await.unwrap();
+
+    let mut tasks = Vec::new();
+    for worker in 0..8 {
+        let store = store.clone();
+        let token = token.clone();
+        tasks.push(tokio::spawn(async move {
+            for i in 0..50 {
+                let item = format!("item-{worker}-{i}");
+                store.touch(&token, Some(&item)).await.unwrap();
+                let viewed = store.recently_viewed(&token).await.unwrap();
+                assert!(viewed.len() <= 10, "{} items", viewed.len());
+            }
+        }));
+    }
+    for task in tasks {
+        task.await.unwrap();
+    }
+    assert_eq!(store.recently_viewed(&token).await.unwrap().len(), 10);
+    store.logout(&token).await.unwrap();
+}
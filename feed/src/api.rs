@@ -0,0 +1,490 @@
+use std::convert::Infallible;
+use std::net::SocketAddr;
+
+use axum::async_trait;
+use axum::extract::{ConnectInfo, FromRequestParts, Path, Query, State};
+use axum::http::request::Parts;
+use axum::http::{header, HeaderMap, StatusCode};
+use axum::response::{IntoResponse, Response};
+use axum::routing::{get, post};
+use axum::{Json, Router};
+use fred::error::{RedisError, RedisErrorKind};
+use fred::interfaces::KeysInterface;
+use serde::{Deserialize, Serialize};
+
+use crate::listing::{ArticleOrder, Cursor, Page};
+use crate::syndication::{FeedFormat, FeedSource};
+use crate::{Article, ArticleStore, FeedError, Revision, SearchQuery};
+
+/// Page size used when the request doesn't specify one.
+const DEFAULT_PAGE_SIZE: usize = 25;
+/// Larger pages are truncated to this size.
+const MAX_PAGE_SIZE: usize = 100;
+
+/// HTTP JSON API over the store.
+///
+/// | Method | Path                           | Body            |
+/// | ------ | ------------------------------ | --------------- |
+/// | GET    | `/articles`                    | `ListingQuery`  |
+/// | POST   | `/articles`                    | `PostArticle`   |
+/// | GET    | `/articles/:id`                |                 |
+/// | POST   | `/articles/:id/vote`           | `Vote`          |
+/// | POST   | `/articles/:id/unvote`         | `Unvote`        |
+/// | POST   | `/articles/:id/groups`         | `ChangeGroups`  |
+/// | POST   | `/articles/:id/edit`           | `EditArticle`   |
+/// | GET    | `/articles/:id/revisions`      |                 |
+/// | GET    | `/groups/:group/articles`      | `ListingQuery`  |
+/// | GET    | `/search`                      | `SearchText`    |
+/// | GET    | `/users/:name/front`           | `ListingQuery`  |
+/// | GET    | `/users/:name/follows`         |                 |
+/// | POST   | `/users/:name/follows`         | `ChangeFollows` |
+/// | GET    | `/feeds/:format/top`           |                 |
+/// | GET    | `/feeds/:format/newest`        |                 |
+/// | GET    | `/feeds/:format/groups/:group` |                 |
+///
+/// Listing parameters are passed in the query string, feed `format`
+/// is `rss` or `atom`.
+pub fn router(store: ArticleStore) -> Router {
+    Router::new()
+        .route("/articles", get(list_articles).post(post_article))
+        .route("/articles/:id", get(get_article))
+        .route("/articles/:id/vote", post(vote))
+        .route("/articles/:id/unvote", post(unvote))
+        .route("/articles/:id/groups", post(change_groups))
+        .route("/articles/:id/edit", post(edit_article))
+        .route("/articles/:id/revisions", get(get_revisions))
+        .route("/groups/:group/articles", get(list_group_articles))
+        .route("/search", get(search))
+        .route("/users/:name/front", get(front_page))
+        .route(
+            "/users/:name/follows",
+            get(get_follows).post(change_follows),
+        )
+        .route("/feeds/:format/top", get(top_feed))
+        .route("/feeds/:format/newest", get(newest_feed))
+        .route("/feeds/:format/groups/:group", get(group_feed))
+        .with_state(store)
+}
+
+/// Serve the API on `addr` until the process is stopped.
+/// Peer address of the connection is used for per-IP rate limits.
+pub async fn serve(store: ArticleStore, addr: &str) -> std::io::Result<()> {
+    let listener = tokio::net::TcpListener::bind(addr).await?;
+    let app = router(store).into_make_service_with_connect_info::<SocketAddr>();
+    axum::serve(listener, app).await
+}
+
+// ───── Request and response types ───────────────────────────────────────── //
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PostArticle {
+    pub user: String,
+    pub title: String,
+    pub link: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EditArticle {
+    pub user: String,
+    pub title: String,
+    pub link: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Posted {
+    pub id: u32,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Vote {
+    pub user: String,
+    pub upvote: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Unvote {
+    pub user: String,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ChangeGroups {
+    #[serde(default)]
+    pub add: Vec<String>,
+    #[serde(default)]
+    pub remove: Vec<String>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct ListingQuery {
+    /// `score` (default) or `time`.
+    pub order: Option<ArticleOrder>,
+    pub page_size: Option<usize>,
+    /// `next` token of the previous page.
+    pub cursor: Option<String>,
+}
+
+/// Search query in the `SearchQuery` syntax, passed together with
+/// `ListingQuery` parameters.
+#[derive(Debug, Clone, Deserialize)]
+pub struct SearchText {
+    pub q: String,
+}
+
+/// Groups and authors followed by the user.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Follows {
+    #[serde(default)]
+    pub groups: Vec<String>,
+    #[serde(default)]
+    pub authors: Vec<String>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ChangeFollows {
+    #[serde(default)]
+    pub follow: Follows,
+    #[serde(default)]
+    pub unfollow: Follows,
+}
+
+/// Page of articles with the token of the next page.
+#[derive(Debug, Clone, Serialize)]
+pub struct PageResponse {
+    pub articles: Vec<Article>,
+    pub next: Option<String>,
+}
+
+impl From<Page> for PageResponse {
+    fn from(page: Page) -> Self {
+        PageResponse {
+            articles: page.articles,
+            next: page.next.map(|cursor| cursor.to_string()),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ErrorResponse {
+    pub error: String,
+}
+
+/// Error of the handler, converted to the JSON error response.
+#[derive(Debug)]
+pub struct ApiError {
+    status: StatusCode,
+    error: FeedError,
+}
+
+impl From<FeedError> for ApiError {
+    fn from(error: FeedError) -> Self {
+        let status = match &error {
+            FeedError::Redis(e)
+                if *e.kind() == RedisErrorKind::InvalidArgument =>
+            {
+                StatusCode::BAD_REQUEST
+            }
+            FeedError::Redis(_) => StatusCode::INTERNAL_SERVER_ERROR,
+            FeedError::Throttled { .. } => StatusCode::TOO_MANY_REQUESTS,
+            FeedError::NotFound(_) => StatusCode::NOT_FOUND,
+            FeedError::Banned(_) | FeedError::Forbidden(_) => {
+                StatusCode::FORBIDDEN
+            }
+        };
+        ApiError { status, error }
+    }
+}
+
+impl From<RedisError> for ApiError {
+    fn from(error: RedisError) -> Self {
+        FeedError::from(error).into()
+    }
+}
+
+impl IntoResponse for ApiError {
+    fn into_response(self) -> Response {
+        let body = Json(ErrorResponse {
+            error: self.error.to_string(),
+        });
+        match self.error {
+            FeedError::Throttled { retry_after } => {
+                // Retry-After is in whole seconds, round up
+                let seconds = retry_after.as_millis().div_ceil(1000);
+                let retry_after = [(header::RETRY_AFTER, seconds.to_string())];
+                (self.status, retry_after, body).into_response()
+            }
+            _ => (self.status, body).into_response(),
+        }
+    }
+}
+
+/// Store acting on behalf of the caller, with `ArticleStore::with_ip`
+/// of the peer address. Requests without connection info, like in tests,
+/// get the shared store.
+struct Caller(ArticleStore);
+
+#[async_trait]
+impl FromRequestParts<ArticleStore> for Caller {
+    type Rejection = Infallible;
+
+    async fn from_request_parts(
+        parts: &mut Parts,
+        store: &ArticleStore,
+    ) -> Result<Self, Self::Rejection> {
+        let store = match parts.extensions.get::<ConnectInfo<SocketAddr>>() {
+            Some(ConnectInfo(addr)) => store.with_ip(&addr.ip().to_string()),
+            None => store.clone(),
+        };
+        Ok(Caller(store))
+    }
+}
+
+// ───── Handlers ─────────────────────────────────────────────────────────── //
+
+/// 201 for a new article, 200 if the link is a duplicate
+/// and the existing article is returned.
+async fn post_article(
+    Caller(store): Caller,
+    Json(request): Json<PostArticle>,
+) -> Result<(StatusCode, Json<Posted>), ApiError> {
+    let (id, created) = store
+        .submit_article(&request.user, &request.title, &request.link)
+        .await?;
+    let status = if created {
+        StatusCode::CREATED
+    } else {
+        StatusCode::OK
+    };
+    Ok((status, Json(Posted { id })))
+}
+
+async fn get_article(
+    State(store): State<ArticleStore>,
+    Path(id): Path<u32>,
+) -> Result<Json<Article>, ApiError> {
+    match store.get_article(id).await? {
+        Some(article) => Ok(Json(article)),
+        None => Err(FeedError::NotFound(Article::key(id)).into()),
+    }
+}
+
+async fn vote(
+    Caller(store): Caller,
+    Path(id): Path<u32>,
+    Json(request): Json<Vote>,
+) -> Result<StatusCode, ApiError> {
+    ensure_article(&store, id).await?;
+    store
+        .article_vote(&request.user, id, request.upvote)
+        .await?;
+    Ok(StatusCode::NO_CONTENT)
+}
+
+async fn unvote(
+    State(store): State<ArticleStore>,
+    Path(id): Path<u32>,
+    Json(request): Json<Unvote>,
+) -> Result<StatusCode, ApiError> {
+    ensure_article(&store, id).await?;
+    store.unvote(&request.user, id).await?;
+    Ok(StatusCode::NO_CONTENT)
+}
+
+async fn change_groups(
+    State(store): State<ArticleStore>,
+    Path(id): Path<u32>,
+    Json(request): Json<ChangeGroups>,
+) -> Result<StatusCode, ApiError> {
+    ensure_article(&store, id).await?;
+    let add = request.add.iter().map(String::as_str).collect::<Vec<_>>();
+    let remove = request
+        .remove
+        .iter()
+        .map(String::as_str)
+        .collect::<Vec<_>>();
+    store.add_remove_groups(id, &add, &remove).await?;
+    Ok(StatusCode::NO_CONTENT)
+}
+
+async fn edit_article(
+    State(store): State<ArticleStore>,
+    Path(id): Path<u32>,
+    Json(request): Json<EditArticle>,
+) -> Result<StatusCode, ApiError> {
+    store
+        .edit_article(&request.user, id, &request.title, &request.link)
+        .await?;
+    Ok(StatusCode::NO_CONTENT)
+}
+
+async fn get_revisions(
+    State(store): State<ArticleStore>,
+    Path(id): Path<u32>,
+) -> Result<Json<Vec<Revision>>, ApiError> {
+    ensure_article(&store, id).await?;
+    Ok(Json(store.get_article_revisions(id).await?))
+}
+
+async fn list_articles(
+    State(store): State<ArticleStore>,
+    Query(query): Query<ListingQuery>,
+) -> Result<Json<PageResponse>, ApiError> {
+    let (order, page_size, cursor) = query.parse()?;
+    let page = store
+        .get_articles_page(order, page_size, cursor.as_ref())
+        .await?;
+    Ok(Json(page.into()))
+}
+
+async fn list_group_articles(
+    State(store): State<ArticleStore>,
+    Path(group): Path<String>,
+    Query(query): Query<ListingQuery>,
+) -> Result<Json<PageResponse>, ApiError> {
+    let (order, page_size, cursor) = query.parse()?;
+    let page = store
+        .get_group_articles_page(&group, order, page_size, cursor.as_ref())
+        .await?;
+    Ok(Json(page.into()))
+}
+
+async fn top_feed(
+    State(store): State<ArticleStore>,
+    Path(format): Path<String>,
+    headers: HeaderMap,
+) -> Result<Response, ApiError> {
+    export_feed(&store, FeedSource::Top, &format, &headers).await
+}
+
+async fn newest_feed(
+    State(store): State<ArticleStore>,
+    Path(format): Path<String>,
+    headers: HeaderMap,
+) -> Result<Response, ApiError> {
+    export_feed(&store, FeedSource::Newest, &format, &headers).await
+}
+
+async fn group_feed(
+    State(store): State<ArticleStore>,
+    Path((format, group)): Path<(String, String)>,
+    headers: HeaderMap,
+) -> Result<Response, ApiError> {
+    export_feed(&store, FeedSource::Group(&group), &format, &headers).await
+}
+
+async fn search(
+    State(store): State<ArticleStore>,
+    Query(text): Query<SearchText>,
+    Query(query): Query<ListingQuery>,
+) -> Result<Json<PageResponse>, ApiError> {
+    let (order, page_size, cursor) = query.parse()?;
+    let page = store
+        .search_articles(
+            &SearchQuery::parse(&text.q),
+            order,
+            page_size,
+            cursor.as_ref(),
+        )
+        .await?;
+    Ok(Json(page.into()))
+}
+
+async fn front_page(
+    State(store): State<ArticleStore>,
+    Path(name): Path<String>,
+    Query(query): Query<ListingQuery>,
+) -> Result<Json<PageResponse>, ApiError> {
+    let (order, page_size, cursor) = query.parse()?;
+    let page = store
+        .get_front_page(&name, order, page_size, cursor.as_ref())
+        .await?;
+    Ok(Json(page.into()))
+}
+
+async fn get_follows(
+    State(store): State<ArticleStore>,
+    Path(name): Path<String>,
+) -> Result<Json<Follows>, ApiError> {
+    Ok(Json(Follows {
+        groups: store.get_followed_groups(&name).await?,
+        authors: store.get_followed_authors(&name).await?,
+    }))
+}
+
+async fn change_follows(
+    State(store): State<ArticleStore>,
+    Path(name): Path<String>,
+    Json(request): Json<ChangeFollows>,
+) -> Result<StatusCode, ApiError> {
+    for group in request.follow.groups.iter() {
+        store.follow_group(&name, group).await?;
+    }
+    for author in request.follow.authors.iter() {
+        store.follow_author(&name, author).await?;
+    }
+    for group in request.unfollow.groups.iter() {
+        store.unfollow_group(&name, group).await?;
+    }
+    for author in request.unfollow.authors.iter() {
+        store.unfollow_author(&name, author).await?;
+    }
+    Ok(StatusCode::NO_CONTENT)
+}
+
+// ───── Helpers ──────────────────────────────────────────────────────────── //
+
+impl ListingQuery {
+    fn parse(&self) -> Result<(ArticleOrder, usize, Option<Cursor>), ApiError> {
+        let cursor = match &self.cursor {
+            Some(cursor) => Some(cursor.parse().map_err(bad_request)?),
+            None => None,
+        };
+        let page_size = self
+            .page_size
+            .unwrap_or(DEFAULT_PAGE_SIZE)
+            .clamp(1, MAX_PAGE_SIZE);
+        Ok((self.order.unwrap_or(ArticleOrder::Score), page_size, cursor))
+    }
+}
+
+fn bad_request(error: RedisError) -> ApiError {
+    ApiError {
+        status: StatusCode::BAD_REQUEST,
+        error: FeedError::Redis(error),
+    }
+}
+
+/// Voting and grouping silently ignore missing articles,
+/// API reports them as 404.
+async fn ensure_article(store: &ArticleStore, id: u32) -> Result<(), ApiError> {
+    let key = Article::key(id);
+    if !store.client.exists::<bool, _>(&key).await? {
+        return Err(FeedError::NotFound(key).into());
+    }
+    Ok(())
+}
+
+async fn export_feed(
+    store: &ArticleStore,
+    source: FeedSource<'_>,
+    format: &str,
+    headers: &HeaderMap,
+) -> Result<Response, ApiError> {
+    let format = match format {
+        "rss" => FeedFormat::Rss,
+        "atom" => FeedFormat::Atom,
+        other => {
+            return Err(
+                FeedError::NotFound(format!("feed format {other}")).into()
+            )
+        }
+    };
+    let host = headers
+        .get(header::HOST)
+        .and_then(|host| host.to_str().ok())
+        .unwrap_or("localhost");
+    let site = format!("http://{host}");
+    let xml = store
+        .export_feed(source, format, &site, DEFAULT_PAGE_SIZE)
+        .await?;
+    Ok(([(header::CONTENT_TYPE, format.content_type())], xml).into_response())
+}
@@ -0,0 +1,164 @@
+use std::time::Duration;
+
+use fred::error::RedisError;
+use fred::interfaces::{LuaInterface, SortedSetsInterface};
+
+use crate::listing::{Cursor, Page};
+use crate::{get_sys_time_in_secs, Article, ArticleStore, ONE_WEEK_IN_SECONDS};
+
+/// How many articles are archived per one `zrangebyscore` call.
+const ARCHIVE_BATCH: i64 = 100;
+
+impl ArticleStore {
+    /// Archive all articles whose voting window is over.
+    ///
+    /// For each article posted more than a week ago we delete its
+    /// `upvoted:{id}` and `downvoted:{id}` sets, store the final score
+    /// in the `score` field of the article hash, and move the article
+    /// from `score:` and `time:` zsets to the `archive:` zset.
+    /// Returns the number of archived articles.
+    pub async fn archive_articles(&self) -> Result<usize, RedisError> {
+        let week_ago = get_sys_time_in_secs() - ONE_WEEK_IN_SECONDS as u64;
+        let mut archived = 0;
+
+        loop {
+            let keys: Vec<String> = self
+                .client
+                .zrangebyscore(
+                    "time:",
+                    "-inf",
+                    week_ago as f64,
+                    false,
+                    Some((0, ARCHIVE_BATCH)),
+                )
+                .await?;
+            if keys.is_empty() {
+                return Ok(archived);
+            }
+
+            for key in keys.iter() {
+                let Some(id) = Article::id_from_key(key) else {
+                    continue;
+                };
+                archived += self
+                    .client
+                    .eval::<usize, _, _, _>(
+                        ARCHIVE_LUA,
+                        vec![
+                            key.clone(),
+                            format!("upvoted:{id}"),
+                            format!("downvoted:{id}"),
+                        ],
+                        None::<String>,
+                    )
+                    .await?;
+            }
+        }
+    }
+
+    /// This task should run in background, it archives articles
+    /// every `interval`. Errors are logged, and archiving is retried
+    /// after the next interval.
+    pub async fn archive_task(&self, interval: Duration) {
+        loop {
+            if let Err(e) = self.archive_articles().await {
+                eprintln!("Archiving failed: {e}");
+            }
+            tokio::time::sleep(interval).await;
+        }
+    }
+
+    /// Archived articles, the newest first.
+    pub async fn get_archived_articles_page(
+        &self,
+        page_size: usize,
+        cursor: Option<&Cursor>,
+    ) -> Result<Page, RedisError> {
+        self.get_page("archive:", page_size, cursor).await
+    }
+}
+
+/// Move article `KEYS[1]` to the `archive:` zset, keeping the post time as
+/// a score, freeze its score in the hash and delete vote sets
+/// `KEYS[2]`, `KEYS[3]`.
+const ARCHIVE_LUA: &str = r#"
+local time = redis.call('ZSCORE', 'time:', KEYS[1])
+if not time then
+    return 0
+end
+local score = redis.call('ZSCORE', 'score:', KEYS[1])
+if score then
+    redis.call('HSET', KEYS[1], 'score', score)
+end
+redis.call('ZADD', 'archive:', time, KEYS[1])
+redis.call('ZREM', 'score:', KEYS[1])
+redis.call('ZREM', 'time:', KEYS[1])
+redis.call('DEL', KEYS[2], KEYS[3])
+return 1
+"#;
+
+#[cfg(test)]
+mod tests {
+    use fred::interfaces::{HashesInterface, KeysInterface};
+
+    use super::*;
+    use crate::{init_redis_client, SECONDS_IN_DAY};
+
+    #[tokio::test]
+    async fn old_articles_are_moved_to_archive() {
+        let store = ArticleStore::new(init_redis_client().await);
+        let client = store.client();
+        let article_id = store
+            .post_article(
+                "archive-author",
+                "archive",
+                &crate::unique_link("archive.com"),
+            )
+            .await
+            .unwrap();
+        store
+            .article_vote("archive-voter", article_id, false)
+            .await
+            .unwrap();
+        let article = Article::key(article_id);
+        let score: f64 = client.zscore("score:", &article).await.unwrap();
+
+        // Pretend the article was posted 8 days ago
+        let posted = get_sys_time_in_secs()
+            - ONE_WEEK_IN_SECONDS as u64
+            - SECONDS_IN_DAY as u64;
+        client
+            .zadd::<(), _, _>(
+                "time:",
+                None,
+                None,
+                false,
+                false,
+                (posted as f64, &article),
+            )
+            .await
+            .unwrap();
+        assert!(store.archive_articles().await.unwrap() >= 1);
+
+        let time: Option<f64> = client.zscore("time:", &article).await.unwrap();
+        let live: Option<f64> =
+            client.zscore("score:", &article).await.unwrap();
+        let archived: Option<f64> =
+            client.zscore("archive:", &article).await.unwrap();
+        assert_eq!((time, live), (None, None));
+        assert_eq!(archived, Some(posted as f64));
+
+        let frozen: f64 = client.hget(&article, "score").await.unwrap();
+        assert_eq!(frozen, score);
+        let votes: i64 = client
+            .exists(vec![
+                format!("upvoted:{article_id}"),
+                format!("downvoted:{article_id}"),
+            ])
+            .await
+            .unwrap();
+        assert_eq!(votes, 0);
+        let article = store.get_article(article_id).await.unwrap().unwrap();
+        assert_eq!(article.score, Some(score));
+    }
+}
@@ -0,0 +1,95 @@
+use std::collections::HashMap;
+use std::str::FromStr;
+
+use fred::error::{RedisError, RedisErrorKind};
+use serde::Serialize;
+
+/// Article, as it is stored in the `article:{article_id}` hash.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Article {
+    pub id: u32,
+    pub title: String,
+    pub link: String,
+    pub author: String,
+    /// Unix timestamp of the moment the article was posted.
+    pub time: u64,
+    pub upvotes: i64,
+    pub downvotes: i64,
+    /// Number of comments.
+    pub comments: i64,
+    /// Final score, frozen when the article is archived.
+    pub score: Option<f64>,
+    /// Unix timestamp of the moment moderator hid the article.
+    pub hidden: Option<u64>,
+    /// Unix timestamp of the last edit.
+    pub edited: Option<u64>,
+}
+
+impl Article {
+    /// Key of the article hash, `article:{article_id}`.
+    pub fn key(id: u32) -> String {
+        format!("article:{id}")
+    }
+
+    /// Extract article id from the `article:{article_id}` key.
+    pub fn id_from_key(key: &str) -> Option<u32> {
+        key.strip_prefix("article:")?.parse().ok()
+    }
+
+    /// Build an article from the fields returned by `HGETALL`.
+    pub(crate) fn from_hash(
+        id: u32,
+        mut hash: HashMap<String, String>,
+    ) -> Result<Self, RedisError> {
+        Ok(Article {
+            id,
+            title: take_field(&mut hash, "title")?,
+            link: take_field(&mut hash, "link")?,
+            author: take_field(&mut hash, "author")?,
+            time: parse_field(&mut hash, "time")?,
+            upvotes: parse_field(&mut hash, "upvotes")?,
+            downvotes: parse_field(&mut hash, "downvotes")?,
+            comments: parse_optional_field(&mut hash, "comments")?.unwrap_or(0),
+            score: parse_optional_field(&mut hash, "score")?,
+            hidden: parse_optional_field(&mut hash, "hidden")?,
+            edited: parse_optional_field(&mut hash, "edited")?,
+        })
+    }
+}
+
+// ───── Helpers ──────────────────────────────────────────────────────────── //
+
+pub(crate) fn take_field(
+    hash: &mut HashMap<String, String>,
+    field: &str,
+) -> Result<String, RedisError> {
+    hash.remove(field).ok_or_else(|| {
+        RedisError::new(
+            RedisErrorKind::Parse,
+            format!("Field `{field}` is missing"),
+        )
+    })
+}
+
+pub(crate) fn parse_field<T: FromStr>(
+    hash: &mut HashMap<String, String>,
+    field: &str,
+) -> Result<T, RedisError> {
+    take_field(hash, field)?.parse().map_err(|_| {
+        RedisError::new(
+            RedisErrorKind::Parse,
+            format!("Field `{field}` has invalid value"),
+        )
+    })
+}
+
+pub(crate) fn parse_optional_field<T: FromStr>(
+    hash: &mut HashMap<String, String>,
+    field: &str,
+) -> Result<Option<T>, RedisError> {
+    if hash.contains_key(field) {
+        parse_field(hash, field).map(Some)
+    } else {
+        Ok(None)
+    }
+}
@@ -0,0 +1,335 @@
+use std::collections::HashMap;
+
+use fred::error::{RedisError, RedisErrorKind};
+use fred::interfaces::{
+    HashesInterface, KeysInterface, SetsInterface, SortedSetsInterface,
+};
+
+use crate::article::{parse_field, parse_optional_field, take_field};
+use crate::listing::ArticleOrder;
+use crate::rate_limit::Action;
+use crate::voting::VoteTarget;
+use crate::{
+    get_sys_time_in_secs, Article, ArticleStore, FeedError, ONE_WEEK_IN_SECONDS,
+};
+
+/// Comment, as it is stored in the `comment:{comment_id}` hash.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Comment {
+    pub id: u32,
+    pub article_id: u32,
+    /// Comment this one replies to, `None` for top-level comments.
+    pub parent: Option<u32>,
+    pub author: String,
+    pub text: String,
+    /// Unix timestamp of the moment the comment was posted.
+    pub time: u64,
+    pub upvotes: i64,
+    pub downvotes: i64,
+}
+
+impl Comment {
+    /// Key of the comment hash, `comment:{comment_id}`.
+    pub fn key(id: u32) -> String {
+        format!("comment:{id}")
+    }
+
+    /// Extract comment id from the `comment:{comment_id}` key.
+    pub fn id_from_key(key: &str) -> Option<u32> {
+        key.strip_prefix("comment:")?.parse().ok()
+    }
+
+    fn from_hash(
+        id: u32,
+        mut hash: HashMap<String, String>,
+    ) -> Result<Self, RedisError> {
+        Ok(Comment {
+            id,
+            article_id: parse_field(&mut hash, "article")?,
+            parent: parse_optional_field(&mut hash, "parent")?,
+            author: take_field(&mut hash, "author")?,
+            text: take_field(&mut hash, "text")?,
+            time: parse_field(&mut hash, "time")?,
+            upvotes: parse_field(&mut hash, "upvotes")?,
+            downvotes: parse_field(&mut hash, "downvotes")?,
+        })
+    }
+}
+
+/// Comment with its replies, the best first.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CommentNode {
+    pub comment: Comment,
+    pub replies: Vec<CommentNode>,
+}
+
+/// Zset of article comments, ordered by `order`:
+/// `comments:score:{article_id}` or `comments:time:{article_id}`.
+pub(crate) fn comments_key(order: ArticleOrder, article_id: u32) -> String {
+    format!("comments:{}{}", order.key(), article_id)
+}
+
+impl VoteTarget {
+    pub(crate) fn comment(comment_id: u32, article_id: u32) -> Self {
+        VoteTarget {
+            hash: Comment::key(comment_id),
+            upvoted: format!("comment_upvoted:{comment_id}"),
+            downvoted: format!("comment_downvoted:{comment_id}"),
+            time_zset: comments_key(ArticleOrder::Time, article_id),
+            score_zset: comments_key(ArticleOrder::Score, article_id),
+            track_voted: false,
+        }
+    }
+}
+
+impl ArticleStore {
+    /// Post a comment to the article, or a reply to the `parent` comment
+    /// of the same article. Comment is added to the `comments:time:{id}`
+    /// and `comments:score:{id}` zsets of the article, and article
+    /// `comments` counter is incremented.
+    pub async fn post_comment(
+        &self,
+        user: &str,
+        article_id: u32,
+        parent: Option<u32>,
+        text: &str,
+    ) -> Result<u32, FeedError> {
+        self.ensure_not_banned(user).await?;
+        self.check_rate_limit(Action::Post, user).await?;
+
+        let client = &self.client;
+        let article = Article::key(article_id);
+        if !client.exists::<bool, _>(&article).await? {
+            return Err(FeedError::NotFound(article));
+        }
+        if let Some(parent) = parent {
+            let parent_article: Option<u32> =
+                client.hget(Comment::key(parent), "article").await?;
+            if parent_article != Some(article_id) {
+                return Err(FeedError::NotFound(Comment::key(parent)));
+            }
+        }
+
+        let comment_id = client.incr::<u32, _>("comment:").await?;
+        let comment = Comment::key(comment_id);
+        let target = VoteTarget::comment(comment_id, article_id);
+        let now = get_sys_time_in_secs();
+
+        let mut fields = vec![
+            ("article", article_id.to_string()),
+            ("author", user.to_string()),
+            ("text", text.to_string()),
+            ("time", now.to_string()),
+            ("upvotes", "1".to_string()),
+            ("downvotes", "0".to_string()),
+        ];
+        if let Some(parent) = parent {
+            fields.push(("parent", parent.to_string()));
+        }
+
+        let pipe = client.pipeline();
+        pipe.hset::<(), _, _>(&comment, fields).await?;
+        pipe.sadd::<(), _, _>(&target.upvoted, user).await?;
+        pipe.expire::<(), _>(&target.upvoted, ONE_WEEK_IN_SECONDS)
+            .await?;
+        pipe.zadd::<(), _, _>(
+            &target.time_zset,
+            None,
+            None,
+            false,
+            false,
+            vec![(now as f64, &comment)],
+        )
+        .await?;
+        pipe.zadd::<(), _, _>(
+            &target.score_zset,
+            None,
+            None,
+            false,
+            false,
+            vec![(self.scoring.score(now, 1, 0, now), &comment)],
+        )
+        .await?;
+        pipe.hincrby::<(), _, _>(&article, "comments", 1).await?;
+        pipe.all::<()>().await?;
+
+        Ok(comment_id)
+    }
+
+    /// Vote for the comment, with the same rules as `article_vote`.
+    pub async fn comment_vote(
+        &self,
+        user: &str,
+        comment_id: u32,
+        is_upvote: bool,
+    ) -> Result<(), FeedError> {
+        self.ensure_not_banned(user).await?;
+        self.check_rate_limit(Action::Vote, user).await?;
+        let target = self.comment_vote_target(comment_id).await?;
+        self.vote(user, &target, is_upvote).await?;
+        Ok(())
+    }
+
+    /// Retract user's vote for the comment, if any.
+    pub async fn comment_unvote(
+        &self,
+        user: &str,
+        comment_id: u32,
+    ) -> Result<(), FeedError> {
+        let target = self.comment_vote_target(comment_id).await?;
+        self.retract_vote(user, &target).await?;
+        Ok(())
+    }
+
+    /// Flat list of article comments, ordered by `order`.
+    pub async fn get_comments(
+        &self,
+        article_id: u32,
+        order: ArticleOrder,
+    ) -> Result<Vec<Comment>, RedisError> {
+        let keys: Vec<String> = self
+            .client
+            .zrevrange(comments_key(order, article_id), 0, -1, false)
+            .await?;
+        self.fetch_comments(&keys).await
+    }
+
+    /// Article comments arranged in threads, each level is ordered by
+    /// score, the best first. Replies to deleted comments are shown
+    /// at the top level.
+    pub async fn get_comment_thread(
+        &self,
+        article_id: u32,
+    ) -> Result<Vec<CommentNode>, RedisError> {
+        let comments =
+            self.get_comments(article_id, ArticleOrder::Score).await?;
+        Ok(build_thread(comments))
+    }
+
+    async fn comment_vote_target(
+        &self,
+        comment_id: u32,
+    ) -> Result<VoteTarget, FeedError> {
+        let article_id: Option<u32> = self
+            .client
+            .hget(Comment::key(comment_id), "article")
+            .await?;
+        match article_id {
+            Some(article_id) => Ok(VoteTarget::comment(comment_id, article_id)),
+            None => Err(FeedError::NotFound(Comment::key(comment_id))),
+        }
+    }
+
+    /// Fetch all comments data in one round trip.
+    async fn fetch_comments(
+        &self,
+        keys: &[String],
+    ) -> Result<Vec<Comment>, RedisError> {
+        self.fetch_hashes(keys, |key, comment_data| {
+            let id = Comment::id_from_key(key).ok_or_else(|| {
+                RedisError::new(
+                    RedisErrorKind::Parse,
+                    format!("Invalid comment key: {key}"),
+                )
+            })?;
+            Comment::from_hash(id, comment_data)
+        })
+        .await
+    }
+}
+
+/// Arrange comments into threads, keeping their order on each level.
+/// Replies to comments missing from the list go to the top level.
+fn build_thread(comments: Vec<Comment>) -> Vec<CommentNode> {
+    let known = comments.iter().map(|c| c.id).collect::<Vec<_>>();
+    let mut children: HashMap<Option<u32>, Vec<Comment>> = HashMap::new();
+    for comment in comments.into_iter() {
+        let parent = comment.parent.filter(|p| known.contains(p));
+        children.entry(parent).or_default().push(comment);
+    }
+
+    fn build(
+        parent: Option<u32>,
+        children: &mut HashMap<Option<u32>, Vec<Comment>>,
+    ) -> Vec<CommentNode> {
+        children
+            .remove(&parent)
+            .unwrap_or_default()
+            .into_iter()
+            .map(|comment| {
+                let replies = build(Some(comment.id), children);
+                CommentNode { comment, replies }
+            })
+            .collect()
+    }
+    build(None, &mut children)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn comment(id: u32, parent: Option<u32>) -> Comment {
+        Comment {
+            id,
+            article_id: 1,
+            parent,
+            author: "author".to_string(),
+            text: format!("comment {id}"),
+            time: 0,
+            upvotes: 1,
+            downvotes: 0,
+        }
+    }
+
+    /// Thread as `(id, replies)` pairs, for readable assertions.
+    fn shape(nodes: &[CommentNode]) -> Vec<(u32, Vec<(u32, usize)>)> {
+        nodes
+            .iter()
+            .map(|node| {
+                let replies = node
+                    .replies
+                    .iter()
+                    .map(|reply| (reply.comment.id, reply.replies.len()))
+                    .collect();
+                (node.comment.id, replies)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn replies_are_nested_in_list_order() {
+        let thread = build_thread(vec![
+            comment(3, None),
+            comment(5, Some(1)),
+            comment(1, None),
+            comment(4, Some(1)),
+            comment(6, Some(4)),
+        ]);
+        assert_eq!(
+            shape(&thread),
+            vec![(3, vec![]), (1, vec![(5, 0), (4, 1)])]
+        );
+        assert_eq!(thread[1].replies[1].replies[0].comment.id, 6);
+    }
+
+    #[test]
+    fn replies_to_deleted_comments_move_to_top_level() {
+        // Comment 2 is deleted, its replies keep their own replies
+        let thread = build_thread(vec![
+            comment(1, None),
+            comment(3, Some(2)),
+            comment(4, Some(3)),
+            comment(5, Some(2)),
+        ]);
+        assert_eq!(
+            shape(&thread),
+            vec![(1, vec![]), (3, vec![(4, 0)]), (5, vec![])]
+        );
+    }
+
+    #[test]
+    fn empty_list_gives_empty_thread() {
+        assert_eq!(build_thread(Vec::new()), Vec::new());
+    }
+}
@@ -0,0 +1,186 @@
+use fred::error::{RedisError, RedisErrorKind};
+use fred::interfaces::{HashesInterface, ListInterface, LuaInterface};
+use serde::{Deserialize, Serialize};
+
+use crate::link::normalize_link;
+use crate::{get_sys_time_in_secs, Article, ArticleStore, FeedError};
+
+/// Previous version of the article, stored as JSON
+/// in the `revisions:{article_id}` list.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Revision {
+    pub title: String,
+    pub link: String,
+    /// Unix timestamp of the edit which replaced this revision.
+    pub replaced: u64,
+}
+
+/// List of article revisions, the newest first.
+pub(crate) fn revisions_key(article_id: u32) -> String {
+    format!("revisions:{article_id}")
+}
+
+impl ArticleStore {
+    /// Change title and link of the article. Only the author can edit,
+    /// during the store edit window after posting. Previous title and link
+    /// are pushed to the `revisions:{article_id}` list, and the `edited`
+    /// field of the article hash is set. The `link:` index follows
+    /// the new link, and the title words index follows the new title.
+    /// Fails with `FeedError::Forbidden` if the new link was posted as
+    /// another article within the store duplicate window.
+    pub async fn edit_article(
+        &self,
+        user: &str,
+        article_id: u32,
+        title: &str,
+        link: &str,
+    ) -> Result<(), FeedError> {
+        self.ensure_not_banned(user).await?;
+
+        let article = Article::key(article_id);
+        let now = get_sys_time_in_secs();
+        let window_start = now.saturating_sub(self.edit_window.as_secs());
+        // Old link is normalized here, the script fails with 0 if the link
+        // was changed since, and the edit is retried
+        let (edited, old_title) = loop {
+            let Some(old_link): Option<String> =
+                self.client.hget(&article, "link").await?
+            else {
+                return Err(FeedError::NotFound(article));
+            };
+            let (edited, old_title): (i64, String) = self
+                .client
+                .eval(
+                    EDIT_LUA,
+                    vec![article.clone(), revisions_key(article_id)],
+                    vec![
+                        user.to_string(),
+                        window_start.to_string(),
+                        now.to_string(),
+                        title.to_string(),
+                        link.to_string(),
+                        normalize_link(&old_link),
+                        normalize_link(link),
+                        article_id.to_string(),
+                        old_link,
+                        self.duplicate_window.as_secs().to_string(),
+                    ],
+                )
+                .await?;
+            if edited != 0 {
+                break (edited, old_title);
+            }
+        };
+        match edited {
+            -1 => Err(FeedError::NotFound(article)),
+            -2 => Err(FeedError::Forbidden(format!(
+                "{user} is not the author of {article}"
+            ))),
+            -3 => Err(FeedError::Forbidden(format!(
+                "edit window of {article} is over"
+            ))),
+            -4 => Err(FeedError::Forbidden(format!(
+                "{link} was recently posted as another article"
+            ))),
+            _ => {
+                self.reindex_article(article_id, Some(&old_title), Some(title))
+                    .await?;
+                Ok(())
+            }
+        }
+    }
+
+    /// Previous versions of the article, the newest first.
+    pub async fn get_article_revisions(
+        &self,
+        article_id: u32,
+    ) -> Result<Vec<Revision>, RedisError> {
+        let revisions: Vec<String> =
+            self.client.lrange(revisions_key(article_id), 0, -1).await?;
+        revisions
+            .iter()
+            .map(|revision| {
+                serde_json::from_str(revision).map_err(|e| {
+                    RedisError::new(
+                        RedisErrorKind::Parse,
+                        format!("Invalid revision: {e}"),
+                    )
+                })
+            })
+            .collect()
+    }
+}
+
+/// Edit article `KEYS[1]` by user `ARGV[1]`, if it was posted after
+/// `ARGV[2]` and its link is still `ARGV[9]`. Current title and link are
+/// pushed to the revisions list `KEYS[2]` with `ARGV[3]` edit time, then
+/// replaced by `ARGV[4]` and `ARGV[5]`. `link:` index entry `ARGV[6]`
+/// of article `ARGV[8]` is moved to `ARGV[7]`, unless it points to
+/// another article posted less than `ARGV[10]` seconds before `ARGV[3]`.
+/// Returns `{1, old_title}` on success, `{0, ''}` if the link was changed,
+/// `{-1, ''}` if article doesn't exist, `{-2, ''}` if user is not
+/// the author, `{-3, ''}` if edit window is over and `{-4, ''}` if
+/// the new link is a recent duplicate.
+const EDIT_LUA: &str = r#"
+local article = redis.call('HMGET', KEYS[1], 'author', 'time', 'title', 'link')
+if not article[1] then
+    return {-1, ''}
+end
+if article[1] ~= ARGV[1] then
+    return {-2, ''}
+end
+if tonumber(article[2]) < tonumber(ARGV[2]) then
+    return {-3, ''}
+end
+if article[4] ~= ARGV[9] then
+    return {0, ''}
+end
+local now = tonumber(ARGV[3])
+local window = tonumber(ARGV[10])
+if ARGV[6] ~= ARGV[7] and window > 0 then
+    local existing = redis.call('HGET', 'link:', ARGV[7])
+    if existing and existing ~= ARGV[8] then
+        local time = redis.call('HGET', 'article:' .. existing, 'time')
+        if time and tonumber(time) >= now - window then
+            return {-4, ''}
+        end
+    end
+end
+local revision = cjson.encode({
+    title = article[3],
+    link = article[4],
+    replaced = now,
+})
+redis.call('LPUSH', KEYS[2], revision)
+redis.call('HSET', KEYS[1],
+    'title', ARGV[4],
+    'link', ARGV[5],
+    'edited', ARGV[3])
+if ARGV[6] ~= ARGV[7] then
+    if redis.call('HGET', 'link:', ARGV[6]) == ARGV[8] then
+        redis.call('HDEL', 'link:', ARGV[6])
+    end
+    redis.call('HSET', 'link:', ARGV[7], ARGV[8])
+end
+return {1, article[3]}
+"#;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn revision_is_read_in_any_field_order() {
+        // cjson doesn't keep field order
+        let json = r#"{"replaced":17,"link":"example.com","title":"Cats"}"#;
+        let revision: Revision = serde_json::from_str(json).unwrap();
+        assert_eq!(
+            revision,
+            Revision {
+                title: "Cats".to_string(),
+                link: "example.com".to_string(),
+                replaced: 17,
+            }
+        );
+    }
+}
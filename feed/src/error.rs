@@ -0,0 +1,53 @@
+use std::fmt;
+use std::time::Duration;
+
+use fred::error::RedisError;
+
+/// Error returned by feed operations which can be refused
+/// for a reason other than redis failure.
+#[derive(Debug)]
+pub enum FeedError {
+    Redis(RedisError),
+    /// Caller exceeded the rate limit, next attempt may succeed
+    /// after `retry_after`.
+    Throttled {
+        retry_after: Duration,
+    },
+    /// Referenced article or comment doesn't exist.
+    NotFound(String),
+    /// User is in the `banned:` set and can't write.
+    Banned(String),
+    /// User is not allowed to perform the action, with explanation.
+    Forbidden(String),
+}
+
+impl fmt::Display for FeedError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            FeedError::Redis(e) => write!(f, "Redis error: {e}"),
+            FeedError::Throttled { retry_after } => write!(
+                f,
+                "Too many requests, retry after {} ms",
+                retry_after.as_millis()
+            ),
+            FeedError::NotFound(what) => write!(f, "Not found: {what}"),
+            FeedError::Banned(user) => write!(f, "User is banned: {user}"),
+            FeedError::Forbidden(why) => write!(f, "Forbidden: {why}"),
+        }
+    }
+}
+
+impl std::error::Error for FeedError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            FeedError::Redis(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<RedisError> for FeedError {
+    fn from(e: RedisError) -> Self {
+        FeedError::Redis(e)
+    }
+}
@@ -0,0 +1,241 @@
+use fred::error::{RedisError, RedisErrorKind};
+use fred::interfaces::{
+    ClientLike, EventInterface, LuaInterface, PubsubInterface,
+};
+use futures::Stream;
+use serde::{Deserialize, Serialize};
+use tokio::sync::broadcast::error::RecvError;
+
+use crate::groups::article_groups_key;
+use crate::scoring::Scored;
+use crate::{Article, ArticleStore};
+
+/// Event published when articles change, serialized as JSON
+/// with the `type` field.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum FeedEvent {
+    /// New article was posted (global channel), or added to the group
+    /// (group channel).
+    Posted {
+        article_id: u32,
+        title: String,
+        link: String,
+        author: String,
+        time: u64,
+    },
+    /// Vote for the article was added, changed or retracted.
+    Voted {
+        article_id: u32,
+        upvotes: i64,
+        downvotes: i64,
+        /// Current score, `None` if the article is archived.
+        score: Option<f64>,
+    },
+}
+
+impl FeedEvent {
+    fn posted(article: &Article) -> Self {
+        FeedEvent::Posted {
+            article_id: article.id,
+            title: article.title.clone(),
+            link: article.link.clone(),
+            author: article.author.clone(),
+            time: article.time,
+        }
+    }
+
+    fn to_json(&self) -> Result<String, RedisError> {
+        serde_json::to_string(self)
+            .map_err(|e| RedisError::new(RedisErrorKind::Parse, format!("{e}")))
+    }
+}
+
+/// Channel with events of all articles (`events:`),
+/// or of the articles of the group (`events:{group_name}`).
+pub fn events_channel(group: Option<&str>) -> String {
+    format!("events:{}", group.unwrap_or_default())
+}
+
+impl ArticleStore {
+    /// Subscribe to events of the given groups, or to the global channel
+    /// if `groups` is empty. Uses a separate connection, which is closed
+    /// when the stream is dropped. Events lost because the consumer was
+    /// too slow, and messages which are not valid events, are skipped.
+    pub async fn subscribe_events(
+        &self,
+        groups: &[&str],
+    ) -> Result<impl Stream<Item = FeedEvent> + Send + 'static, RedisError>
+    {
+        let channels = if groups.is_empty() {
+            vec![events_channel(None)]
+        } else {
+            groups
+                .iter()
+                .map(|group| events_channel(Some(group)))
+                .collect()
+        };
+
+        let subscriber = Subscriber(self.client.clone_new());
+        let _connection = subscriber.0.init().await?;
+        let messages = subscriber.0.message_rx();
+        subscriber.0.subscribe(channels).await?;
+
+        Ok(futures::stream::unfold(
+            (subscriber, messages),
+            |(subscriber, mut messages)| async move {
+                loop {
+                    let message = match messages.recv().await {
+                        Ok(message) => message,
+                        Err(RecvError::Lagged(_)) => continue,
+                        Err(RecvError::Closed) => return None,
+                    };
+                    let Some(payload) = message.value.as_str() else {
+                        continue;
+                    };
+                    if let Ok(event) = serde_json::from_str(&payload) {
+                        return Some((event, (subscriber, messages)));
+                    }
+                }
+            },
+        ))
+    }
+
+    /// Publish `Posted` event of the new article to the global channel,
+    /// or to the channels of the groups the article was added to.
+    pub(crate) async fn publish_posted(
+        &self,
+        article: &Article,
+        groups: &[&str],
+    ) -> Result<(), RedisError> {
+        let payload = FeedEvent::posted(article).to_json()?;
+        if groups.is_empty() {
+            return self.client.publish(events_channel(None), payload).await;
+        }
+        let pipe = self.client.pipeline();
+        for group in groups.iter() {
+            pipe.publish::<(), _, _>(events_channel(Some(group)), &payload)
+                .await?;
+        }
+        pipe.all().await
+    }
+
+    /// Publish `Voted` event with the counters and score written by the
+    /// vote to the global channel and to the channels of the article
+    /// groups, found in its `groups:{article_id}` set.
+    pub(crate) async fn publish_voted(
+        &self,
+        article_id: u32,
+        scored: Scored,
+    ) -> Result<(), RedisError> {
+        let event = FeedEvent::Voted {
+            article_id,
+            upvotes: scored.upvotes,
+            downvotes: scored.downvotes,
+            score: scored.score,
+        };
+        self.client
+            .eval(
+                PUBLISH_LUA,
+                vec![events_channel(None), article_groups_key(article_id)],
+                event.to_json()?,
+            )
+            .await
+    }
+}
+
+/// Subscribed connection, closed when the event stream is dropped.
+struct Subscriber(fred::clients::RedisClient);
+
+impl Drop for Subscriber {
+    fn drop(&mut self) {
+        let client = self.0.clone();
+        if let Ok(runtime) = tokio::runtime::Handle::try_current() {
+            runtime.spawn(async move {
+                let _ = client.quit().await;
+            });
+        }
+    }
+}
+
+/// Publish message `ARGV[1]` to the global channel `KEYS[1]` and to the
+/// `events:{group_name}` channels of the groups in the `KEYS[2]` set.
+const PUBLISH_LUA: &str = r#"
+redis.call('PUBLISH', KEYS[1], ARGV[1])
+for _, group in ipairs(redis.call('SMEMBERS', KEYS[2])) do
+    redis.call('PUBLISH', 'events:' .. group, ARGV[1])
+end
+"#;
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use fred::interfaces::SortedSetsInterface;
+    use futures::StreamExt;
+
+    use super::*;
+    use crate::init_redis_client;
+
+    #[test]
+    fn events_are_tagged_json() {
+        let event = FeedEvent::Voted {
+            article_id: 7,
+            upvotes: 3,
+            downvotes: 1,
+            score: None,
+        };
+        let json = event.to_json().unwrap();
+        assert_eq!(
+            json,
+            r#"{"type":"voted","article_id":7,"upvotes":3,"downvotes":1,"score":null}"#
+        );
+        assert_eq!(serde_json::from_str::<FeedEvent>(&json).unwrap(), event);
+    }
+
+    #[tokio::test]
+    async fn group_subscribers_receive_votes() {
+        let store = ArticleStore::new(init_redis_client().await);
+        let article_id = store
+            .post_article(
+                "events-author",
+                "events",
+                &crate::unique_link("events.com"),
+            )
+            .await
+            .unwrap();
+        let group = format!("events{article_id}");
+        store
+            .add_remove_groups(article_id, &[group.as_str()], &[])
+            .await
+            .unwrap();
+
+        let events = store.subscribe_events(&[group.as_str()]).await.unwrap();
+        let mut events = Box::pin(events);
+        store
+            .article_vote("events-voter", article_id, false)
+            .await
+            .unwrap();
+
+        let event = tokio::time::timeout(Duration::from_secs(5), events.next())
+            .await
+            .unwrap()
+            .unwrap();
+        let FeedEvent::Voted {
+            article_id: voted,
+            upvotes,
+            downvotes,
+            score,
+        } = event
+        else {
+            panic!("unexpected event {event:?}");
+        };
+        assert_eq!((voted, upvotes, downvotes), (article_id, 1, 1));
+        let expected: Option<f64> = store
+            .client()
+            .zscore("score:", Article::key(article_id))
+            .await
+            .unwrap();
+        assert_eq!(score, expected);
+    }
+}
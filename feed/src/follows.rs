@@ -0,0 +1,229 @@
+use fred::error::RedisError;
+use fred::interfaces::{
+    KeysInterface, SetsInterface, SortedSetsInterface, TransactionInterface,
+};
+use fred::types::AggregateOptions;
+
+use crate::groups::{validate_group_name, GROUP_CACHE_TTL};
+use crate::listing::{ArticleOrder, Cursor, Page};
+use crate::users::submitted_key;
+use crate::{get_sys_time_in_secs, ArticleStore, SECONDS_IN_DAY};
+
+/// Boost added to the score of articles from followed groups and authors
+/// on the personalized front page, in units of the ordering zset.
+///
+/// Defaults suit `LinearDecay` scoring and the `time:` order: articles
+/// of followed groups move one day forward, articles of followed authors
+/// move two days forward. Article both in a followed group and by
+/// a followed author gets both boosts. Author boost is a tiny fraction
+/// smaller for older articles, it is scaled by the post time.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct FollowWeights {
+    pub group: f64,
+    pub author: f64,
+}
+
+impl Default for FollowWeights {
+    fn default() -> Self {
+        FollowWeights {
+            group: SECONDS_IN_DAY as f64,
+            author: 2. * SECONDS_IN_DAY as f64,
+        }
+    }
+}
+
+/// Set of groups followed by the user.
+fn followed_groups_key(user: &str) -> String {
+    format!("follows:groups:{user}")
+}
+
+/// Set of authors followed by the user.
+fn followed_authors_key(user: &str) -> String {
+    format!("follows:authors:{user}")
+}
+
+/// Keys of cached front pages of the user.
+fn front_page_keys(user: &str) -> [String; 2] {
+    [
+        format!("cache:{}front:{user}", ArticleOrder::Score.key()),
+        format!("cache:{}front:{user}", ArticleOrder::Time.key()),
+    ]
+}
+
+impl ArticleStore {
+    /// Follow the group. Returns `false` if the user already follows it.
+    pub async fn follow_group(
+        &self,
+        user: &str,
+        group: &str,
+    ) -> Result<bool, RedisError> {
+        validate_group_name(group)?;
+        self.change_follows(followed_groups_key(user), user, group, true)
+            .await
+    }
+
+    /// Returns `false` if the user doesn't follow the group.
+    pub async fn unfollow_group(
+        &self,
+        user: &str,
+        group: &str,
+    ) -> Result<bool, RedisError> {
+        self.change_follows(followed_groups_key(user), user, group, false)
+            .await
+    }
+
+    /// Follow the author. Returns `false` if the user already follows them.
+    pub async fn follow_author(
+        &self,
+        user: &str,
+        author: &str,
+    ) -> Result<bool, RedisError> {
+        self.change_follows(followed_authors_key(user), user, author, true)
+            .await
+    }
+
+    /// Returns `false` if the user doesn't follow the author.
+    pub async fn unfollow_author(
+        &self,
+        user: &str,
+        author: &str,
+    ) -> Result<bool, RedisError> {
+        self.change_follows(followed_authors_key(user), user, author, false)
+            .await
+    }
+
+    /// Names of groups followed by the user, sorted.
+    pub async fn get_followed_groups(
+        &self,
+        user: &str,
+    ) -> Result<Vec<String>, RedisError> {
+        let mut groups: Vec<String> =
+            self.client.smembers(followed_groups_key(user)).await?;
+        groups.sort();
+        Ok(groups)
+    }
+
+    /// Names of authors followed by the user, sorted.
+    pub async fn get_followed_authors(
+        &self,
+        user: &str,
+    ) -> Result<Vec<String>, RedisError> {
+        let mut authors: Vec<String> =
+            self.client.smembers(followed_authors_key(user)).await?;
+        authors.sort();
+        Ok(authors)
+    }
+
+    /// Cursor-based personalized front page of the user, ordered by
+    /// `order` with articles of followed groups and authors boosted by
+    /// the store `FollowWeights`. Cached in the `cache:score:front:{user}`
+    /// or `cache:time:front:{user}` zset for 1 minute. User who follows
+    /// nothing gets the common listing.
+    pub async fn get_front_page(
+        &self,
+        user: &str,
+        order: ArticleOrder,
+        page_size: usize,
+        cursor: Option<&Cursor>,
+    ) -> Result<Page, RedisError> {
+        let destination = format!("cache:{}front:{user}", order.key());
+        if !self.client.exists::<bool, _>(&destination).await? {
+            let groups = self.get_followed_groups(user).await?;
+            let authors = self.get_followed_authors(user).await?;
+            if groups.is_empty() && authors.is_empty() {
+                return self.get_page(order.key(), page_size, cursor).await;
+            }
+            self.cache_front_page(&destination, order, &groups, &authors)
+                .await?;
+        }
+        self.get_page(&destination, page_size, cursor).await
+    }
+
+    /// Union the ordering zset with boosts of followed groups and authors
+    /// into `destination`, keeping only articles of the ordering zset.
+    async fn cache_front_page(
+        &self,
+        destination: &str,
+        order: ArticleOrder,
+        groups: &[String],
+        authors: &[String],
+    ) -> Result<(), RedisError> {
+        // Use transaction, so readers never see partially built zset
+        let multi = self.client.multi();
+        let mut keys = vec![order.key().to_string()];
+
+        // Group sets have score 1, an article in several followed groups
+        // is boosted once
+        let groups_key = format!("{destination}:groups");
+        if !groups.is_empty() {
+            multi
+                .zunionstore::<(), _, _, _>(
+                    &groups_key,
+                    groups
+                        .iter()
+                        .map(|group| format!("group:{group}"))
+                        .collect::<Vec<_>>(),
+                    vec![self.follow_weights.group; groups.len()],
+                    Some(AggregateOptions::Max),
+                )
+                .await?;
+            keys.push(groups_key.clone());
+        }
+
+        // Submissions are scored by post time, weighted by `1 / now` it
+        // is just below 1 for any article in the voting window
+        let authors_key = format!("{destination}:authors");
+        if !authors.is_empty() {
+            let weight =
+                self.follow_weights.author / get_sys_time_in_secs() as f64;
+            multi
+                .zunionstore::<(), _, _, _>(
+                    &authors_key,
+                    authors
+                        .iter()
+                        .map(|author| submitted_key(author))
+                        .collect::<Vec<_>>(),
+                    vec![weight; authors.len()],
+                    Some(AggregateOptions::Max),
+                )
+                .await?;
+            keys.push(authors_key.clone());
+        }
+
+        multi
+            .zunionstore::<(), _, _, _>(destination, keys, None, None)
+            .await?;
+        // Boosted articles which are not listed anymore are dropped
+        multi
+            .zinterstore::<(), _, _, _>(
+                destination,
+                vec![destination, order.key()],
+                vec![1., 0.],
+                Some(AggregateOptions::Sum),
+            )
+            .await?;
+        multi.del::<(), _>(vec![groups_key, authors_key]).await?;
+        multi.expire::<(), _>(destination, GROUP_CACHE_TTL).await?;
+        multi.exec::<()>(true).await
+    }
+
+    /// Add `name` to or remove it from the follows set `key`, and drop
+    /// cached front pages of the user.
+    async fn change_follows(
+        &self,
+        key: String,
+        user: &str,
+        name: &str,
+        follow: bool,
+    ) -> Result<bool, RedisError> {
+        let pipe = self.client.pipeline();
+        if follow {
+            pipe.sadd::<(), _, _>(key, name).await?;
+        } else {
+            pipe.srem::<(), _, _>(key, name).await?;
+        }
+        pipe.del::<(), _>(front_page_keys(user).to_vec()).await?;
+        let (changed, _): (bool, i64) = pipe.all().await?;
+        Ok(changed)
+    }
+}
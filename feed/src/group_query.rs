@@ -0,0 +1,256 @@
+use fred::error::RedisError;
+use fred::interfaces::{
+    KeysInterface, SortedSetsInterface, TransactionInterface,
+};
+use fred::types::AggregateOptions;
+
+use crate::groups::{validate_group_name, GROUP_CACHE_TTL};
+use crate::listing::{ArticleOrder, Cursor, Page};
+use crate::ArticleStore;
+
+/// Query over groups, like "articles in programming OR rust
+/// but NOT politics", which is
+/// `GroupQuery::default().any_of(&["programming", "rust"]).none_of(&["politics"])`.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct GroupQuery {
+    /// Article should be in at least one of these groups.
+    pub any: Vec<String>,
+    /// Article should be in each of these groups.
+    pub all: Vec<String>,
+    /// Article should be in none of these groups.
+    pub none: Vec<String>,
+}
+
+impl GroupQuery {
+    pub fn any_of(mut self, groups: &[&str]) -> Self {
+        self.any.extend(groups.iter().map(|g| g.to_string()));
+        self
+    }
+
+    pub fn all_of(mut self, groups: &[&str]) -> Self {
+        self.all.extend(groups.iter().map(|g| g.to_string()));
+        self
+    }
+
+    pub fn none_of(mut self, groups: &[&str]) -> Self {
+        self.none.extend(groups.iter().map(|g| g.to_string()));
+        self
+    }
+
+    /// Canonical form of the query, the same for queries which differ
+    /// only in the order of groups. Used as a part of the cache key,
+    /// it is unambiguous as group names can't contain `,`, `;` or `=`.
+    fn canonical(&self) -> String {
+        fn list(groups: &[String]) -> String {
+            let mut groups = groups.to_vec();
+            groups.sort();
+            groups.dedup();
+            groups.join(",")
+        }
+        format!(
+            "any={};all={};none={}",
+            list(&self.any),
+            list(&self.all),
+            list(&self.none)
+        )
+    }
+}
+
+impl ArticleStore {
+    /// Cursor-based listing of articles matching the group query,
+    /// ordered by `order`. Query result is cached in the
+    /// `cache:score:query:{query}` or `cache:time:query:{query}` zset for
+    /// 1 minute.
+    pub async fn get_group_query_page(
+        &self,
+        query: &GroupQuery,
+        order: ArticleOrder,
+        page_size: usize,
+        cursor: Option<&Cursor>,
+    ) -> Result<Page, RedisError> {
+        let destination = self.cache_group_query(query, order).await?;
+        self.get_page(&destination, page_size, cursor).await
+    }
+
+    /// Build the query result zset, if it is not cached yet.
+    /// Returns key of the cached zset.
+    async fn cache_group_query(
+        &self,
+        query: &GroupQuery,
+        order: ArticleOrder,
+    ) -> Result<String, RedisError> {
+        for group in query.any.iter().chain(&query.all).chain(&query.none) {
+            validate_group_name(group)?;
+        }
+        let group_keys = |groups: &[String]| {
+            groups
+                .iter()
+                .map(|group| format!("group:{group}"))
+                .collect::<Vec<_>>()
+        };
+        self.cache_set_query(
+            format!("cache:{}query:{}", order.key(), query.canonical()),
+            &[(order.key(), 1.)],
+            group_keys(&query.any),
+            group_keys(&query.all),
+            group_keys(&query.none),
+        )
+        .await
+    }
+
+    /// Store articles of the `ordering` zsets (union of them, scores
+    /// multiplied by the weights) which are members of at least one of
+    /// `any` sets (if given), of all `all` sets and of none of `none` sets
+    /// into `destination` zset, with their ordering scores, if it is not
+    /// cached yet. Result is cached for 1 minute.
+    pub(crate) async fn cache_set_query(
+        &self,
+        destination: String,
+        ordering: &[(&str, f64)],
+        any: Vec<String>,
+        all: Vec<String>,
+        none: Vec<String>,
+    ) -> Result<String, RedisError> {
+        if self.client.exists::<bool, _>(&destination).await? {
+            return Ok(destination);
+        }
+
+        // Use transaction, so readers never see partially built zset
+        let multi = self.client.multi();
+
+        let order_key = format!("{destination}:order");
+        let ordering_key = match ordering {
+            [(key, weight)] if *weight == 1. => key.to_string(),
+            _ => {
+                let (keys, weights): (Vec<_>, Vec<_>) =
+                    ordering.iter().copied().unzip();
+                multi
+                    .zunionstore::<(), _, _, _>(&order_key, keys, weights, None)
+                    .await?;
+                order_key.clone()
+            }
+        };
+
+        // Keep only articles which are in the ordering zset, their scores
+        // are used, other sets are weighted with 0.
+        let mut keys = vec![ordering_key];
+        let any_key = format!("{destination}:any");
+        if !any.is_empty() {
+            multi
+                .zunionstore::<(), _, _, _>(&any_key, any, None, None)
+                .await?;
+            keys.push(any_key.clone());
+        }
+        keys.extend(all);
+        let mut weights = vec![0.; keys.len()];
+        weights[0] = 1.;
+        multi
+            .zinterstore::<(), _, _, _>(
+                &destination,
+                keys,
+                weights,
+                Some(AggregateOptions::Sum),
+            )
+            .await?;
+
+        if !none.is_empty() {
+            let mut keys = vec![destination.clone()];
+            keys.extend(none);
+            multi.zdiffstore::<(), _, _>(&destination, keys).await?;
+        }
+
+        multi.del::<(), _>(vec![any_key, order_key]).await?;
+        multi.expire::<(), _>(&destination, GROUP_CACHE_TTL).await?;
+        multi.exec::<()>(true).await?;
+
+        Ok(destination)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::init_redis_client;
+
+    #[test]
+    fn canonical_query_ignores_group_order() {
+        let query = GroupQuery::default()
+            .any_of(&["rust", "programming", "rust"])
+            .none_of(&["politics"]);
+        assert_eq!(
+            query.canonical(),
+            "any=programming,rust;all=;none=politics"
+        );
+        let reordered = GroupQuery::default()
+            .none_of(&["politics"])
+            .any_of(&["programming", "rust"]);
+        assert_eq!(query.canonical(), reordered.canonical());
+    }
+
+    #[tokio::test]
+    async fn any_and_none_groups_are_combined() {
+        let store = ArticleStore::new(init_redis_client().await);
+        let mut ids = Vec::new();
+        for i in 0..5 {
+            let id = store
+                .post_article(
+                    &format!("query-author-{i}"),
+                    "query",
+                    &crate::unique_link("query.com"),
+                )
+                .await
+                .unwrap();
+            ids.push(id);
+        }
+        // Group names unique for the run
+        let programming = format!("programming{}", ids[0]);
+        let rust = format!("rust{}", ids[0]);
+        let politics = format!("politics{}", ids[0]);
+        let memberships = [
+            vec![programming.as_str()],
+            vec![rust.as_str()],
+            vec![rust.as_str(), politics.as_str()],
+            vec![],
+            vec![programming.as_str(), rust.as_str()],
+        ];
+        for (id, groups) in ids.iter().zip(memberships.iter()) {
+            store.add_remove_groups(*id, groups, &[]).await.unwrap();
+        }
+
+        let query = GroupQuery::default()
+            .any_of(&[&programming, &rust])
+            .none_of(&[&politics]);
+        let page = store
+            .get_group_query_page(&query, ArticleOrder::Time, 10, None)
+            .await
+            .unwrap();
+        let mut found = page
+            .articles
+            .iter()
+            .map(|article| article.id)
+            .collect::<Vec<_>>();
+        found.sort();
+        assert_eq!(found, vec![ids[0], ids[1], ids[4]]);
+
+        let cached = format!("cache:time:query:{}", query.canonical());
+        let ttl: i64 = store.client().ttl(&cached).await.unwrap();
+        assert!(ttl > 0 && ttl <= GROUP_CACHE_TTL);
+        let any_exists: bool = store
+            .client()
+            .exists(format!("{cached}:any"))
+            .await
+            .unwrap();
+        assert!(!any_exists);
+    }
+
+    #[tokio::test]
+    async fn groups_with_separators_are_rejected() {
+        let store = ArticleStore::new(init_redis_client().await);
+        let query = GroupQuery::default().any_of(&["a,b"]);
+        let error = store
+            .get_group_query_page(&query, ArticleOrder::Time, 10, None)
+            .await
+            .unwrap_err();
+        assert_eq!(*error.kind(), fred::error::RedisErrorKind::InvalidArgument);
+    }
+}
@@ -0,0 +1,343 @@
+use fred::error::{RedisError, RedisErrorKind};
+use fred::interfaces::{
+    KeysInterface, LuaInterface, SetsInterface, SortedSetsInterface,
+};
+use fred::types::AggregateOptions;
+
+use crate::listing::{ArticleOrder, Cursor, Page};
+use crate::{Article, ArticleStore};
+
+/// How long cached `cache:score:{group_name}` and `cache:time:{group_name}`
+/// zsets live, and other cached listings built from them.
+pub(crate) const GROUP_CACHE_TTL: i64 = 60;
+
+impl ArticleStore {
+    /// Register a new group in the `groups:` set.
+    /// Returns `false` if group already exists.
+    pub async fn create_group(&self, group: &str) -> Result<bool, RedisError> {
+        validate_group_name(group)?;
+        self.client.sadd("groups:", group).await
+    }
+
+    /// Names of all registered groups, sorted.
+    pub async fn list_groups(&self) -> Result<Vec<String>, RedisError> {
+        let mut groups: Vec<String> = self.client.smembers("groups:").await?;
+        groups.sort();
+        Ok(groups)
+    }
+
+    /// Number of articles in the group.
+    pub async fn count_group_members(
+        &self,
+        group: &str,
+    ) -> Result<u64, RedisError> {
+        validate_group_name(group)?;
+        self.client.scard(format!("group:{group}")).await
+    }
+
+    /// Rename group with all its articles.
+    /// Returns `false` if group with the `new` name already exists.
+    pub async fn rename_group(
+        &self,
+        old: &str,
+        new: &str,
+    ) -> Result<bool, RedisError> {
+        validate_group_name(old)?;
+        validate_group_name(new)?;
+        let mut keys = vec![format!("group:{old}"), format!("group:{new}")];
+        keys.extend(group_cache_keys(old));
+        keys.extend(group_cache_keys(new));
+        self.client
+            .eval(RENAME_GROUP_LUA, keys, vec![old, new])
+            .await
+    }
+
+    /// Delete group, articles themselves are kept.
+    pub async fn delete_group(&self, group: &str) -> Result<(), RedisError> {
+        validate_group_name(group)?;
+        let mut keys = vec![format!("group:{group}")];
+        keys.extend(group_cache_keys(group));
+        self.client.eval(DELETE_GROUP_LUA, keys, group).await
+    }
+
+    /// Add or remove groups
+    /// Added groups are registered in the `groups:` set, the
+    /// `groups:{article_id}` set of the article follows its groups, and
+    /// cached listings of all touched groups are invalidated.
+    /// `FeedEvent::Posted` is published to the channels of added groups.
+    pub async fn add_remove_groups(
+        &self,
+        article_id: u32,
+        to_add: &[&str],
+        to_remove: &[&str],
+    ) -> Result<(), RedisError> {
+        for group in to_add.iter().chain(to_remove.iter()) {
+            validate_group_name(group)?;
+        }
+        let article = Article::key(article_id);
+        let article_groups = article_groups_key(article_id);
+        let pipe = self.client.pipeline();
+        for group in to_add.iter() {
+            pipe.sadd::<(), _, _>(format!("group:{group}"), &article)
+                .await?;
+            pipe.sadd::<(), _, _>(&article_groups, *group).await?;
+            pipe.sadd::<(), _, _>("groups:", *group).await?;
+        }
+
+        for group in to_remove.iter() {
+            pipe.srem::<(), _, _>(format!("group:{group}"), &article)
+                .await?;
+            pipe.srem::<(), _, _>(&article_groups, *group).await?;
+        }
+
+        let caches = to_add
+            .iter()
+            .chain(to_remove.iter())
+            .flat_map(|group| group_cache_keys(group))
+            .collect::<Vec<_>>();
+        if !caches.is_empty() {
+            pipe.del::<(), _>(caches).await?;
+        }
+        pipe.all::<()>().await?;
+
+        if to_add.is_empty() {
+            return Ok(());
+        }
+        match self.get_article(article_id).await? {
+            Some(article) => self.publish_posted(&article, to_add).await,
+            None => Ok(()),
+        }
+    }
+
+    /// This function caches articles of the same group in the
+    /// `cache:score:{group_name}` zset for 1 minute.
+    /// And returns these articles using `get_articles_ordered_by_score`
+    pub async fn get_group_articles_by_score(
+        &self,
+        group: &str,
+        page: i64,
+    ) -> Result<Vec<Article>, RedisError> {
+        let destination = self.cache_group(group, ArticleOrder::Score).await?;
+        self.get_articles_ordered_by_score(page, &destination).await
+    }
+
+    /// Cursor-based listing of group articles, ordered by `order`.
+    /// Uses `cache:score:{group_name}` or `cache:time:{group_name}` zset.
+    pub async fn get_group_articles_page(
+        &self,
+        group: &str,
+        order: ArticleOrder,
+        page_size: usize,
+        cursor: Option<&Cursor>,
+    ) -> Result<Page, RedisError> {
+        let destination = self.cache_group(group, order).await?;
+        self.get_page(&destination, page_size, cursor).await
+    }
+
+    /// Intersect `group:{group_name}` set with `score:` or `time:` zset,
+    /// and cache result in `cache:score:{group_name}` or
+    /// `cache:time:{group_name}` zset for 1 minute.
+    /// Returns key of the cached zset.
+    async fn cache_group(
+        &self,
+        group: &str,
+        order: ArticleOrder,
+    ) -> Result<String, RedisError> {
+        validate_group_name(group)?;
+        let destination = group_cache_key(group, order);
+
+        // Check is there temporary zset of articles
+        if !self.client.exists::<bool, _>(&destination).await? {
+            // If no, create new. Group set members have score 1,
+            // so we weight them with 0 to keep original scores.
+            let pipe = self.client.pipeline();
+            pipe.zinterstore::<(), _, _, _>(
+                &destination,
+                vec![format!("group:{}", group), order.key().to_string()],
+                vec![0., 1.],
+                Some(AggregateOptions::Sum),
+            )
+            .await?;
+            pipe.expire::<(), _>(&destination, GROUP_CACHE_TTL).await?;
+            pipe.all::<()>().await?;
+        }
+
+        Ok(destination)
+    }
+}
+
+// ───── Helpers ──────────────────────────────────────────────────────────── //
+
+/// Group names are a part of keys, so they can't be empty or contain `:`.
+/// `,`, `;` and `=` separate names in the cached group query keys.
+pub fn validate_group_name(group: &str) -> Result<(), RedisError> {
+    if group.is_empty() || group.contains([':', ',', ';', '=']) {
+        return Err(RedisError::new(
+            RedisErrorKind::InvalidArgument,
+            format!("Invalid group name: {group:?}"),
+        ));
+    }
+    Ok(())
+}
+
+/// Key of the cached listing of the group, `cache:score:{group_name}`
+/// or `cache:time:{group_name}`. Own prefix keeps it apart from
+/// the `score:` and `time:` zsets and their other caches.
+fn group_cache_key(group: &str, order: ArticleOrder) -> String {
+    format!("cache:{}{}", order.key(), group)
+}
+
+/// Set of groups the article is in, `groups:{article_id}`.
+pub(crate) fn article_groups_key(article_id: u32) -> String {
+    format!("groups:{article_id}")
+}
+
+/// Keys of cached listings of the group.
+pub(crate) fn group_cache_keys(group: &str) -> [String; 2] {
+    [
+        group_cache_key(group, ArticleOrder::Score),
+        group_cache_key(group, ArticleOrder::Time),
+    ]
+}
+
+/// Rename group `ARGV[1]` to `ARGV[2]`. `KEYS[1]` and `KEYS[2]` are the old
+/// and the new group sets, other keys are cached listings to drop.
+/// `groups:{article_id}` sets of the group articles are updated.
+/// Returns 0 if the new group already exists.
+const RENAME_GROUP_LUA: &str = r#"
+if redis.call('SISMEMBER', 'groups:', ARGV[2]) == 1
+    or redis.call('EXISTS', KEYS[2]) == 1 then
+    return 0
+end
+for _, article in ipairs(redis.call('SMEMBERS', KEYS[1])) do
+    local id = string.match(article, '^article:(%d+)$')
+    if id then
+        redis.call('SREM', 'groups:' .. id, ARGV[1])
+        redis.call('SADD', 'groups:' .. id, ARGV[2])
+    end
+end
+if redis.call('EXISTS', KEYS[1]) == 1 then
+    redis.call('RENAME', KEYS[1], KEYS[2])
+end
+redis.call('SREM', 'groups:', ARGV[1])
+redis.call('SADD', 'groups:', ARGV[2])
+for i = 3, #KEYS do
+    redis.call('DEL', KEYS[i])
+end
+return 1
+"#;
+
+/// Delete group `ARGV[1]`: its set `KEYS[1]`, cached listings in other
+/// keys, registration in `groups:` and entries in `groups:{article_id}`
+/// sets of its articles.
+const DELETE_GROUP_LUA: &str = r#"
+for _, article in ipairs(redis.call('SMEMBERS', KEYS[1])) do
+    local id = string.match(article, '^article:(%d+)$')
+    if id then
+        redis.call('SREM', 'groups:' .. id, ARGV[1])
+    end
+end
+redis.call('SREM', 'groups:', ARGV[1])
+redis.call('DEL', unpack(KEYS))
+"#;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::init_redis_client;
+
+    async fn cached(store: &ArticleStore, group: &str) -> i64 {
+        store
+            .client()
+            .exists(group_cache_keys(group).to_vec())
+            .await
+            .unwrap()
+    }
+
+    async fn cache_listings(store: &ArticleStore, group: &str) {
+        for order in [ArticleOrder::Score, ArticleOrder::Time] {
+            store
+                .get_group_articles_page(group, order, 10, None)
+                .await
+                .unwrap();
+        }
+        assert_eq!(cached(store, group).await, 2);
+    }
+
+    #[tokio::test]
+    async fn rename_and_delete_keep_groups_consistent() {
+        let store = ArticleStore::new(init_redis_client().await);
+        let mut ids = Vec::new();
+        for _ in 0..2 {
+            let id = store
+                .post_article(
+                    "groups-author",
+                    "groups",
+                    &crate::unique_link("groups.com"),
+                )
+                .await
+                .unwrap();
+            ids.push(id);
+        }
+        // Group names unique for the run
+        let old = format!("old{}", ids[0]);
+        let taken = format!("taken{}", ids[0]);
+        let new = format!("new{}", ids[0]);
+        store.add_remove_groups(ids[0], &[&old], &[]).await.unwrap();
+        store
+            .add_remove_groups(ids[1], &[&old, &taken], &[])
+            .await
+            .unwrap();
+        assert_eq!(store.count_group_members(&old).await.unwrap(), 2);
+        assert_eq!(store.count_group_members(&taken).await.unwrap(), 1);
+
+        assert!(!store.rename_group(&old, &taken).await.unwrap());
+        assert_eq!(store.count_group_members(&old).await.unwrap(), 2);
+        assert_eq!(store.count_group_members(&taken).await.unwrap(), 1);
+
+        cache_listings(&store, &old).await;
+        assert!(store.rename_group(&old, &new).await.unwrap());
+        assert_eq!(cached(&store, &old).await, 0);
+        assert_eq!(store.count_group_members(&old).await.unwrap(), 0);
+        assert_eq!(store.count_group_members(&new).await.unwrap(), 2);
+        let groups = store.list_groups().await.unwrap();
+        assert!(groups.contains(&new) && !groups.contains(&old));
+        let mut article_groups: Vec<String> = store
+            .client()
+            .smembers(article_groups_key(ids[1]))
+            .await
+            .unwrap();
+        article_groups.sort();
+        assert_eq!(article_groups, vec![new.clone(), taken.clone()]);
+
+        cache_listings(&store, &new).await;
+        store.delete_group(&new).await.unwrap();
+        assert_eq!(cached(&store, &new).await, 0);
+        assert_eq!(store.count_group_members(&new).await.unwrap(), 0);
+        assert!(!store.list_groups().await.unwrap().contains(&new));
+        let article_groups: Vec<String> = store
+            .client()
+            .smembers(article_groups_key(ids[1]))
+            .await
+            .unwrap();
+        assert_eq!(article_groups, vec![taken]);
+        // Articles themselves are kept
+        assert!(store.get_article(ids[0]).await.unwrap().is_some());
+    }
+
+    #[test]
+    fn group_names_cant_collide_with_listings() {
+        assert!(validate_group_name("rust").is_ok());
+        assert!(validate_group_name("").is_err());
+        assert!(validate_group_name("front:alice").is_err());
+        assert!(validate_group_name("a,b").is_err());
+        assert!(validate_group_name("a;all=b").is_err());
+        assert_eq!(
+            group_cache_keys("rust"),
+            [
+                "cache:score:rust".to_string(),
+                "cache:time:rust".to_string()
+            ]
+        );
+    }
+}
@@ -0,0 +1,82 @@
+use std::time::SystemTime;
+
+use fred::clients::RedisClient;
+use fred::interfaces::ClientLike;
+use fred::types::RedisConfig;
+
+pub mod api;
+pub mod archive;
+pub mod article;
+pub mod comments;
+pub mod editing;
+pub mod error;
+pub mod events;
+pub mod follows;
+pub mod group_query;
+pub mod groups;
+pub mod link;
+pub mod listing;
+pub mod moderation;
+pub mod posting;
+pub mod rate_limit;
+pub mod scoring;
+pub mod search;
+pub mod seed;
+pub mod store;
+pub mod syndication;
+pub mod trending;
+pub mod users;
+pub mod voting;
+
+pub use article::Article;
+pub use comments::{Comment, CommentNode};
+pub use editing::Revision;
+pub use error::FeedError;
+pub use events::FeedEvent;
+pub use follows::FollowWeights;
+pub use group_query::GroupQuery;
+pub use link::normalize_link;
+pub use listing::{ArticleOrder, Cursor, Page};
+pub use rate_limit::{Action, RateLimit, RateLimits};
+pub use scoring::{
+    policy_by_name, Gravity, LinearDecay, ScoringPolicy, Wilson,
+};
+pub use search::SearchQuery;
+pub use store::ArticleStore;
+pub use syndication::{FeedFormat, FeedSource};
+pub use users::UserProfile;
+
+pub const SECONDS_IN_DAY: i64 = 86_400;
+pub const VOTES_REQUIRED: i64 = 200;
+pub const RATIO: i64 = SECONDS_IN_DAY / VOTES_REQUIRED;
+pub const ONE_WEEK_IN_SECONDS: i64 = SECONDS_IN_DAY * 7;
+
+// ───── Helpers ──────────────────────────────────────────────────────────── //
+
+pub fn get_sys_time_in_secs() -> u64 {
+    match SystemTime::now().duration_since(SystemTime::UNIX_EPOCH) {
+        Ok(n) => n.as_secs(),
+        Err(_) => panic!("SystemTime before UNIX EPOCH!"),
+    }
+}
+
+pub async fn init_redis_client() -> RedisClient {
+    let config = RedisConfig::from_url_centralized(
+        "redis://:ghashy@myredis.orb.local:6379",
+    )
+    .unwrap();
+    let client = RedisClient::new(config, None, None, None);
+    let _connection = client.init().await.unwrap();
+    client
+}
+
+/// Link which was never posted before, so duplicate detection
+/// doesn't return articles of earlier test runs.
+#[cfg(test)]
+pub(crate) fn unique_link(host: &str) -> String {
+    let nanos = SystemTime::now()
+        .duration_since(SystemTime::UNIX_EPOCH)
+        .unwrap()
+        .as_nanos();
+    format!("https://{host}/{nanos}")
+}
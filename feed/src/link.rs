@@ -0,0 +1,105 @@
+/// Query parameters which only track where the visitor came from,
+/// they are dropped during normalization. Names ending with `_` are
+/// prefixes.
+const TRACKING_PARAMS: &[&str] = &[
+    "utm_", "fbclid", "gclid", "yclid", "msclkid", "mc_cid", "mc_eid", "ref",
+    "ref_src",
+];
+
+/// Normalize article link, so the same page posted with different
+/// spelling gets the same key in the `link:` index.
+///
+/// `http` and `https` schemes are considered the same, scheme and host
+/// are lowercased, default ports, fragment, trailing slash and tracking
+/// query parameters are removed. Other query parameters keep their order.
+pub fn normalize_link(link: &str) -> String {
+    let link = link.trim();
+    let (scheme, rest) = match link.split_once("://") {
+        Some((scheme, rest))
+            if !scheme.is_empty()
+                && scheme.chars().all(|c| c.is_ascii_alphanumeric()) =>
+        {
+            (scheme.to_ascii_lowercase(), rest)
+        }
+        _ => ("https".to_string(), link),
+    };
+    let scheme = match scheme.as_str() {
+        "http" => "https".to_string(),
+        _ => scheme,
+    };
+
+    let rest = rest.split('#').next().unwrap_or_default();
+    let (location, query) = match rest.split_once('?') {
+        Some((location, query)) => (location, Some(query)),
+        None => (rest, None),
+    };
+    let (host, path) = match location.find('/') {
+        Some(i) => location.split_at(i),
+        None => (location, ""),
+    };
+    let host = host.to_ascii_lowercase();
+    let host = host
+        .strip_suffix(":80")
+        .or_else(|| host.strip_suffix(":443"))
+        .unwrap_or(&host);
+    let path = path.trim_end_matches('/');
+
+    let params = query
+        .unwrap_or_default()
+        .split('&')
+        .filter(|param| !param.is_empty() && !is_tracking_param(param))
+        .collect::<Vec<_>>();
+
+    let mut normalized = format!("{scheme}://{host}{path}");
+    if !params.is_empty() {
+        normalized.push('?');
+        normalized.push_str(&params.join("&"));
+    }
+    normalized
+}
+
+fn is_tracking_param(param: &str) -> bool {
+    let name = param.split('=').next().unwrap_or_default();
+    let name = name.to_ascii_lowercase();
+    TRACKING_PARAMS
+        .iter()
+        .any(|tracking| match tracking.strip_suffix('_') {
+            Some(_) => name.starts_with(tracking),
+            None => name == *tracking,
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn spellings_of_the_same_page_are_equal() {
+        let expected = "https://example.com/cats";
+        for link in [
+            "https://example.com/cats",
+            "http://example.com/cats/",
+            "HTTPS://Example.COM/cats#comments",
+            "example.com:443/cats",
+            "http://example.com:80/cats?utm_source=feed&fbclid=123",
+        ] {
+            assert_eq!(normalize_link(link), expected, "{link}");
+        }
+    }
+
+    #[test]
+    fn meaningful_parts_are_kept() {
+        assert_eq!(
+            normalize_link("http://example.com/Cats/?page=2&utm_medium=x&q=a"),
+            "https://example.com/Cats?page=2&q=a"
+        );
+        assert_eq!(
+            normalize_link("ftp://example.com:8080/file"),
+            "ftp://example.com:8080/file"
+        );
+        assert_eq!(
+            normalize_link("https://example.com/?reference=1"),
+            "https://example.com?reference=1"
+        );
+    }
+}
@@ -0,0 +1,504 @@
+use std::collections::HashMap;
+use std::fmt;
+use std::str::FromStr;
+
+use fred::error::{RedisError, RedisErrorKind};
+use fred::interfaces::{HashesInterface, LuaInterface, SortedSetsInterface};
+use fred::types::{ZRange, ZRangeBound, ZRangeKind};
+use serde::Deserialize;
+
+use crate::{Article, ArticleStore};
+
+const ARTICLES_PER_PAGE: i64 = 25;
+
+/// Zset used to order articles in a listing.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ArticleOrder {
+    /// `score:` zset, the best first.
+    Score,
+    /// `time:` zset, the newest first.
+    Time,
+}
+
+impl ArticleOrder {
+    pub fn key(&self) -> &'static str {
+        match self {
+            ArticleOrder::Score => "score:",
+            ArticleOrder::Time => "time:",
+        }
+    }
+}
+
+/// Position in a listing, right after the last returned article.
+///
+/// Cursor remembers score and member of the last article, so pages
+/// stay stable when new articles are inserted at the top of the zset.
+/// Use `to_string` and `parse` to pass it to clients as an opaque token.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Cursor {
+    score: f64,
+    member: String,
+}
+
+impl fmt::Display for Cursor {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}:{}", self.score, self.member)
+    }
+}
+
+impl FromStr for Cursor {
+    type Err = RedisError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        // Score never contains `:`, member does (`article:{id}`).
+        s.split_once(':')
+            .and_then(|(score, member)| {
+                Some(Cursor {
+                    score: score.parse().ok()?,
+                    member: member.to_string(),
+                })
+            })
+            .ok_or_else(|| {
+                RedisError::new(
+                    RedisErrorKind::Parse,
+                    format!("Invalid cursor: {s}"),
+                )
+            })
+    }
+}
+
+/// One page of a listing.
+#[derive(Debug, Clone)]
+pub struct Page {
+    pub articles: Vec<Article>,
+    /// Cursor for the next page, `None` if this page is the last one.
+    pub next: Option<Cursor>,
+}
+
+impl ArticleStore {
+    /// Fetch single article, `None` if there is no such article.
+    pub async fn get_article(
+        &self,
+        article_id: u32,
+    ) -> Result<Option<Article>, RedisError> {
+        let article_data: HashMap<String, String> =
+            self.client.hgetall(Article::key(article_id)).await?;
+        if article_data.is_empty() {
+            return Ok(None);
+        }
+        Article::from_hash(article_id, article_data).map(Some)
+    }
+
+    /// Front page: articles from the `score:` zset, the best first.
+    pub async fn get_articles_by_score(
+        &self,
+        page: i64,
+    ) -> Result<Vec<Article>, RedisError> {
+        self.get_articles_ordered_by_score(page, "score:").await
+    }
+
+    /// Fetch `page_size` articles ordered by `order`, starting right after
+    /// `cursor`, or from the top when `cursor` is `None`.
+    pub async fn get_articles_page(
+        &self,
+        order: ArticleOrder,
+        page_size: usize,
+        cursor: Option<&Cursor>,
+    ) -> Result<Page, RedisError> {
+        self.get_page(order.key(), page_size, cursor).await
+    }
+
+    /// Cursor-based pagination over any zset of article keys,
+    /// in descending order.
+    pub(crate) async fn get_page(
+        &self,
+        zset_key: &str,
+        page_size: usize,
+        cursor: Option<&Cursor>,
+    ) -> Result<Page, RedisError> {
+        type ArticleKey = String;
+        type ArticleScore = f64;
+
+        // Fetch one extra entry to know if there is a next page
+        let limit = page_size as i64 + 1;
+        let mut ids: Vec<(ArticleKey, ArticleScore)> = Vec::new();
+        let max = match cursor {
+            Some(cursor) => {
+                ids = self.rest_of_ties(zset_key, cursor, limit).await?;
+                ZRange {
+                    kind: ZRangeKind::Exclusive,
+                    range: ZRangeBound::Score(cursor.score),
+                }
+            }
+            None => ZRange {
+                kind: ZRangeKind::Inclusive,
+                range: ZRangeBound::InfiniteScore,
+            },
+        };
+
+        let rest = limit - ids.len() as i64;
+        if rest > 0 {
+            let more: Vec<(ArticleKey, ArticleScore)> = self
+                .client
+                .zrevrangebyscore(zset_key, max, "-inf", true, Some((0, rest)))
+                .await?;
+            ids.extend(more);
+        }
+
+        let next = if ids.len() > page_size {
+            ids.truncate(page_size);
+            ids.last().map(|(member, score)| Cursor {
+                score: *score,
+                member: member.clone(),
+            })
+        } else {
+            None
+        };
+
+        let keys = ids.into_iter().map(|(key, _)| key).collect::<Vec<_>>();
+        let articles = self.fetch_articles(&keys).await?;
+        Ok(Page { articles, next })
+    }
+
+    /// Up to `limit` entries after the cursor, which share its score.
+    /// Zset orders members with the same score lexicographically, so
+    /// they are found by the rank of the cursor member. If the member
+    /// is gone or rescored, entries with the cursor score are paged
+    /// until members before the cursor member are reached.
+    async fn rest_of_ties(
+        &self,
+        zset_key: &str,
+        cursor: &Cursor,
+        limit: i64,
+    ) -> Result<Vec<(String, f64)>, RedisError> {
+        let pipe = self.client.pipeline();
+        pipe.zscore::<(), _, _>(zset_key, &cursor.member).await?;
+        pipe.zrevrank::<(), _, _>(zset_key, &cursor.member).await?;
+        let (score, rank): (Option<f64>, Option<i64>) = pipe.all().await?;
+        if let (Some(score), Some(rank)) = (score, rank) {
+            if score == cursor.score {
+                let next: Vec<(String, f64)> = self
+                    .client
+                    .zrange(
+                        zset_key,
+                        rank + 1,
+                        rank + limit,
+                        None,
+                        true,
+                        None,
+                        true,
+                    )
+                    .await?;
+                return Ok(next
+                    .into_iter()
+                    .take_while(|(_, score)| *score == cursor.score)
+                    .collect());
+            }
+        }
+
+        let mut ties = Vec::new();
+        let mut offset = 0;
+        loop {
+            let batch: Vec<(String, f64)> = self
+                .client
+                .zrevrangebyscore(
+                    zset_key,
+                    cursor.score,
+                    cursor.score,
+                    true,
+                    Some((offset, limit)),
+                )
+                .await?;
+            let done = (batch.len() as i64) < limit;
+            ties.extend(
+                batch
+                    .into_iter()
+                    .filter(|(key, _)| key.as_str() < cursor.member.as_str()),
+            );
+            if done || ties.len() as i64 >= limit {
+                break;
+            }
+            offset += limit;
+        }
+        ties.truncate(limit as usize);
+        Ok(ties)
+    }
+
+    /// This function fetches articles info ordered by score, using
+    /// given zset (`score:` or temporary `score:{group_name}`),
+    /// and each article's hset.
+    /// Both zset range and all hsets are read in one round trip,
+    /// with `FETCH_PAGE_LUA` script.
+    pub(crate) async fn get_articles_ordered_by_score(
+        &self,
+        page: i64,
+        zset_key: &str,
+    ) -> Result<Vec<Article>, RedisError> {
+        let start = (page - 1) * ARTICLES_PER_PAGE;
+        let end = start + ARTICLES_PER_PAGE - 1;
+
+        // Script returns pairs in zset order, so order is perserved
+        let pairs: Vec<(String, HashMap<String, String>)> = self
+            .client
+            .eval(FETCH_PAGE_LUA, zset_key, vec![start, end])
+            .await?;
+
+        pairs
+            .into_iter()
+            .map(|(key, article_data)| {
+                Article::from_hash(parse_article_key(&key)?, article_data)
+            })
+            .collect()
+    }
+
+    /// Fetch all articles data with pipeline, in one round trip.
+    pub(crate) async fn fetch_articles(
+        &self,
+        keys: &[String],
+    ) -> Result<Vec<Article>, RedisError> {
+        self.fetch_hashes(keys, |key, article_data| {
+            Article::from_hash(parse_article_key(key)?, article_data)
+        })
+        .await
+    }
+
+    /// Fetch hashes of all keys with pipeline, in one round trip, and
+    /// convert them with `parse`. Keys without hash are skipped, they were
+    /// deleted since the key was read.
+    pub(crate) async fn fetch_hashes<T>(
+        &self,
+        keys: &[String],
+        parse: impl Fn(&str, HashMap<String, String>) -> Result<T, RedisError>,
+    ) -> Result<Vec<T>, RedisError> {
+        if keys.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        let pipe = self.client.pipeline();
+        for key in keys.iter() {
+            pipe.hgetall::<(), _>(key).await?;
+        }
+        let hashes = pipe.try_all::<HashMap<String, String>>().await;
+
+        keys.iter()
+            .zip(hashes)
+            .filter_map(|(key, hash)| match hash {
+                Ok(hash) if hash.is_empty() => None,
+                hash => Some(hash.and_then(|hash| parse(key, hash))),
+            })
+            .collect()
+    }
+}
+
+/// Fetch page of zset `KEYS[1]` in reverse order, from `ARGV[1]` to
+/// `ARGV[2]` rank, with hash of each member.
+/// Members without hash (deleted articles) are skipped.
+const FETCH_PAGE_LUA: &str = r#"
+local keys = redis.call('ZREVRANGE', KEYS[1], ARGV[1], ARGV[2])
+local result = {}
+for _, key in ipairs(keys) do
+    local hash = redis.call('HGETALL', key)
+    if #hash > 0 then
+        table.insert(result, {key, hash})
+    end
+end
+return result
+"#;
+
+// ───── Helpers ──────────────────────────────────────────────────────────── //
+
+fn parse_article_key(key: &str) -> Result<u32, RedisError> {
+    Article::id_from_key(key).ok_or_else(|| {
+        RedisError::new(
+            RedisErrorKind::Parse,
+            format!("Invalid article key: {key}"),
+        )
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use fred::interfaces::{KeysInterface, SortedSetsInterface};
+
+    use super::*;
+    use crate::init_redis_client;
+
+    /// Page through `zset_key` by two, from the given cursor.
+    async fn page_ids(
+        store: &ArticleStore,
+        zset_key: &str,
+        mut cursor: Option<Cursor>,
+    ) -> Vec<u32> {
+        let mut ids = Vec::new();
+        loop {
+            let page = store.get_page(zset_key, 2, cursor.as_ref()).await;
+            let page = page.unwrap();
+            ids.extend(page.articles.iter().map(|article| article.id));
+            match page.next {
+                Some(next) => cursor = Some(next),
+                None => return ids,
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn pages_with_equal_scores_are_linked() {
+        let store = ArticleStore::new(init_redis_client().await);
+        let mut keys = Vec::new();
+        for i in 0..5 {
+            let id = store
+                .post_article(
+                    &format!("ties-author-{i}"),
+                    "ties",
+                    &crate::unique_link("ties.com"),
+                )
+                .await
+                .unwrap();
+            keys.push(Article::key(id));
+        }
+        let zset_key = format!("ties:{}", keys[0]);
+        // Four articles tie, the last one is below them
+        let entries = keys
+            .iter()
+            .enumerate()
+            .map(|(i, key)| (if i < 4 { 1. } else { 0. }, key.clone()))
+            .collect::<Vec<_>>();
+        let client = store.client();
+        client
+            .zadd::<(), _, _>(&zset_key, None, None, false, false, entries)
+            .await
+            .unwrap();
+
+        let mut expected = keys.clone();
+        expected[..4].sort_by(|a, b| b.cmp(a));
+        let expected = expected
+            .iter()
+            .map(|key| parse_article_key(key).unwrap())
+            .collect::<Vec<_>>();
+        assert_eq!(page_ids(&store, &zset_key, None).await, expected);
+
+        // Cursor member is gone, the rest of the ties is still found
+        let cursor = Cursor {
+            score: 1.,
+            member: Article::key(expected[1]),
+        };
+        client
+            .zrem::<(), _, _>(&zset_key, &cursor.member)
+            .await
+            .unwrap();
+        assert_eq!(
+            page_ids(&store, &zset_key, Some(cursor)).await,
+            expected[2..]
+        );
+        client.del::<(), _>(&zset_key).await.unwrap();
+    }
+}
+
+#[cfg(test)]
+mod benchmark {
+    use std::{future::Future, pin::Pin};
+
+    use super::*;
+    use crate::{get_sys_time_in_secs, init_redis_client};
+
+    type FetchPageFn = Box<
+        dyn Fn(
+            ArticleStore,
+            i64,
+            &'static str,
+        ) -> Pin<
+            Box<dyn Future<Output = Result<Vec<Article>, RedisError>>>,
+        >,
+    >;
+
+    async fn fetch_page_old_version(
+        store: ArticleStore,
+        page: i64,
+        zset_key: &str,
+    ) -> Result<Vec<Article>, RedisError> {
+        let start = (page - 1) * ARTICLES_PER_PAGE;
+        let end = start + ARTICLES_PER_PAGE - 1;
+        let keys: Vec<String> =
+            store.client.zrevrange(zset_key, start, end, false).await?;
+        let mut articles = Vec::new();
+        for key in keys.iter() {
+            let article_data: HashMap<String, String> =
+                store.client.hgetall(key).await?;
+            articles.push(Article::from_hash(
+                parse_article_key(key)?,
+                article_data,
+            )?);
+        }
+        Ok(articles)
+    }
+
+    async fn fetch_page_pipeline_version(
+        store: ArticleStore,
+        page: i64,
+        zset_key: &str,
+    ) -> Result<Vec<Article>, RedisError> {
+        let start = (page - 1) * ARTICLES_PER_PAGE;
+        let end = start + ARTICLES_PER_PAGE - 1;
+        let keys: Vec<String> =
+            store.client.zrevrange(zset_key, start, end, false).await?;
+        store.fetch_articles(&keys).await
+    }
+
+    async fn fetch_page_script_version(
+        store: ArticleStore,
+        page: i64,
+        zset_key: &str,
+    ) -> Result<Vec<Article>, RedisError> {
+        store.get_articles_ordered_by_score(page, zset_key).await
+    }
+
+    fn force_boxed<F, R>(f: F) -> FetchPageFn
+    where
+        F: Fn(ArticleStore, i64, &'static str) -> R + 'static,
+        R: Future<Output = Result<Vec<Article>, RedisError>> + 'static,
+    {
+        Box::new(move |s, p, k| Box::pin(f(s, p, k)))
+    }
+
+    #[tokio::test]
+    async fn benchmark_fetch_page() {
+        let store = ArticleStore::new(init_redis_client().await);
+        // Make sure there is at least one full page
+        for i in 0..ARTICLES_PER_PAGE {
+            store
+                .post_article(
+                    "bench",
+                    &format!("title {i}"),
+                    &crate::unique_link("bench.com"),
+                )
+                .await
+                .unwrap();
+        }
+        let duration = 5;
+        for (i, f) in vec![
+            force_boxed(fetch_page_script_version),
+            force_boxed(fetch_page_pipeline_version),
+            force_boxed(fetch_page_old_version),
+        ]
+        .into_iter()
+        .enumerate()
+        {
+            let mut count = 0;
+            let start = get_sys_time_in_secs();
+            let end = start + duration;
+            while get_sys_time_in_secs() < end {
+                count += 1;
+                let articles = f(store.clone(), 1, "score:").await.unwrap();
+                assert_eq!(articles.len(), ARTICLES_PER_PAGE as usize);
+            }
+            let delta = get_sys_time_in_secs() - start;
+            println!(
+                "{i}: count: {}, delta: {}, count/delta: {}",
+                count,
+                delta,
+                count / delta
+            );
+        }
+    }
+}
@@ -0,0 +1,352 @@
+use fred::error::RedisError;
+use fred::interfaces::{LuaInterface, SetsInterface, SortedSetsInterface};
+
+use crate::groups::{article_groups_key, group_cache_keys};
+use crate::listing::{Cursor, Page};
+use crate::trending::velocity_key;
+use crate::{
+    get_sys_time_in_secs, Article, ArticleStore, FeedError, ONE_WEEK_IN_SECONDS,
+};
+
+impl ArticleStore {
+    /// Flag the article as inappropriate. Each user flags an article
+    /// once, article position in the `flags:` zset is the number of users
+    /// who flagged it.
+    /// Returns `false` if the user already flagged the article.
+    pub async fn flag_article(
+        &self,
+        user: &str,
+        article_id: u32,
+    ) -> Result<bool, FeedError> {
+        self.ensure_not_banned(user).await?;
+        let article = Article::key(article_id);
+        let flagged: i64 = self
+            .client
+            .eval(
+                FLAG_LUA,
+                vec![
+                    article.clone(),
+                    format!("flagged:{article_id}"),
+                    "flags:".to_string(),
+                ],
+                user,
+            )
+            .await?;
+        match flagged {
+            -1 => Err(FeedError::NotFound(article)),
+            flagged => Ok(flagged == 1),
+        }
+    }
+
+    /// Articles flagged by users, the most flagged first.
+    pub async fn get_flagged_articles_page(
+        &self,
+        page_size: usize,
+        cursor: Option<&Cursor>,
+    ) -> Result<Page, RedisError> {
+        self.get_page("flags:", page_size, cursor).await
+    }
+
+    /// Hide the article: remove it from the `score:`, `time:`, `archive:`
+    /// and `trending:` listings and cached group listings, drop its vote
+    /// counters so `refresh_trending` doesn't bring it back, and record
+    /// moderator and reason in the article hash. Article is kept and added
+    /// to the `hidden:` zset for audit, voting for it is not possible.
+    /// Returns `false` if there is no such article or it is already hidden.
+    pub async fn hide_article(
+        &self,
+        article_id: u32,
+        moderator: &str,
+        reason: &str,
+    ) -> Result<bool, RedisError> {
+        let article = Article::key(article_id);
+        let hidden: bool = self
+            .client
+            .eval(
+                HIDE_LUA,
+                vec![article.clone(), velocity_key(article_id)],
+                vec![
+                    moderator.to_string(),
+                    reason.to_string(),
+                    get_sys_time_in_secs().to_string(),
+                ],
+            )
+            .await?;
+        if !hidden {
+            return Ok(false);
+        }
+
+        let groups: Vec<String> =
+            self.client.smembers(article_groups_key(article_id)).await?;
+        if !groups.is_empty() {
+            let pipe = self.client.pipeline();
+            for group in groups.iter() {
+                for cache in group_cache_keys(group) {
+                    pipe.zrem::<(), _, _>(cache, &article).await?;
+                }
+            }
+            pipe.all::<()>().await?;
+        }
+        Ok(true)
+    }
+
+    /// Return hidden article to listings: to `time:` and `score:` if it
+    /// is still in the voting window, otherwise to `archive:`.
+    /// Returns `false` if the article is not hidden.
+    pub async fn unhide_article(
+        &self,
+        article_id: u32,
+    ) -> Result<bool, RedisError> {
+        let Some(article) = self.get_article(article_id).await? else {
+            return Ok(false);
+        };
+        let now = get_sys_time_in_secs();
+        let score = self.scoring.score(
+            article.time,
+            article.upvotes,
+            article.downvotes,
+            now,
+        );
+        self.client
+            .eval(
+                UNHIDE_LUA,
+                vec![Article::key(article_id)],
+                vec![
+                    score.to_string(),
+                    (now - ONE_WEEK_IN_SECONDS as u64).to_string(),
+                ],
+            )
+            .await
+    }
+
+    /// Hidden articles, the most recently hidden first.
+    pub async fn get_hidden_articles_page(
+        &self,
+        page_size: usize,
+        cursor: Option<&Cursor>,
+    ) -> Result<Page, RedisError> {
+        self.get_page("hidden:", page_size, cursor).await
+    }
+
+    /// Add user to the `banned:` set, banned users can't post, vote
+    /// or flag. Returns `false` if user is already banned.
+    pub async fn ban_user(&self, user: &str) -> Result<bool, RedisError> {
+        self.client.sadd("banned:", user).await
+    }
+
+    /// Remove user from the `banned:` set.
+    /// Returns `false` if user wasn't banned.
+    pub async fn unban_user(&self, user: &str) -> Result<bool, RedisError> {
+        self.client.srem("banned:", user).await
+    }
+
+    pub async fn is_banned(&self, user: &str) -> Result<bool, RedisError> {
+        self.client.sismember("banned:", user).await
+    }
+
+    /// Fails with `FeedError::Banned` if the user is banned.
+    pub(crate) async fn ensure_not_banned(
+        &self,
+        user: &str,
+    ) -> Result<(), FeedError> {
+        if self.is_banned(user).await? {
+            return Err(FeedError::Banned(user.to_string()));
+        }
+        Ok(())
+    }
+}
+
+/// Add user `ARGV[1]` to flags set `KEYS[2]` of article `KEYS[1]`, and
+/// increment article score in `KEYS[3]` zset if user wasn't there.
+/// Returns 1 if flag was added, 0 if user already flagged the article
+/// and -1 if article doesn't exist.
+const FLAG_LUA: &str = r#"
+if redis.call('EXISTS', KEYS[1]) == 0 then
+    return -1
+end
+if redis.call('SADD', KEYS[2], ARGV[1]) == 0 then
+    return 0
+end
+redis.call('ZINCRBY', KEYS[3], 1, KEYS[1])
+return 1
+"#;
+
+/// Hide article `KEYS[1]` with vote counters `KEYS[2]` by moderator
+/// `ARGV[1]` with reason `ARGV[2]` at `ARGV[3]` time.
+/// Returns 0 if article doesn't exist or is hidden.
+const HIDE_LUA: &str = r#"
+if redis.call('EXISTS', KEYS[1]) == 0
+    or redis.call('HEXISTS', KEYS[1], 'hidden') == 1 then
+    return 0
+end
+redis.call('ZREM', 'score:', KEYS[1])
+redis.call('ZREM', 'time:', KEYS[1])
+redis.call('ZREM', 'archive:', KEYS[1])
+redis.call('ZREM', 'trending:', KEYS[1])
+redis.call('ZREM', 'votes:', KEYS[1])
+redis.call('DEL', KEYS[2])
+redis.call('ZADD', 'hidden:', ARGV[3], KEYS[1])
+redis.call('HSET', KEYS[1],
+    'hidden', ARGV[3],
+    'hidden_by', ARGV[1],
+    'hidden_reason', ARGV[2])
+return 1
+"#;
+
+/// Return hidden article `KEYS[1]` to listings with score `ARGV[1]`.
+/// Articles posted before `ARGV[2]` go to the `archive:`.
+/// Returns 0 if article is not hidden.
+const UNHIDE_LUA: &str = r#"
+if redis.call('HEXISTS', KEYS[1], 'hidden') == 0 then
+    return 0
+end
+local time = redis.call('HGET', KEYS[1], 'time')
+if tonumber(time) < tonumber(ARGV[2]) then
+    redis.call('ZADD', 'archive:', time, KEYS[1])
+else
+    redis.call('ZADD', 'time:', time, KEYS[1])
+    redis.call('ZADD', 'score:', ARGV[1], KEYS[1])
+end
+redis.call('ZREM', 'hidden:', KEYS[1])
+redis.call('HDEL', KEYS[1], 'hidden', 'hidden_by', 'hidden_reason')
+return 1
+"#;
+
+#[cfg(test)]
+mod tests {
+    use fred::interfaces::HashesInterface;
+
+    use super::*;
+    use crate::listing::ArticleOrder;
+    use crate::{init_redis_client, SECONDS_IN_DAY};
+
+    async fn listed(store: &ArticleStore, key: &str, article: &str) -> bool {
+        let score: Option<f64> =
+            store.client().zscore(key, article).await.unwrap();
+        score.is_some()
+    }
+
+    #[tokio::test]
+    async fn hidden_article_leaves_listings_until_unhidden() {
+        let store = ArticleStore::new(init_redis_client().await);
+        let article_id = store
+            .post_article(
+                "hide-author",
+                "hide",
+                &crate::unique_link("hide.com"),
+            )
+            .await
+            .unwrap();
+        let article = Article::key(article_id);
+        let group = format!("hide-{article_id}");
+        store
+            .add_remove_groups(article_id, &[&group], &[])
+            .await
+            .unwrap();
+        store
+            .article_vote("hide-voter", article_id, true)
+            .await
+            .unwrap();
+        store.refresh_trending().await.unwrap();
+        for order in [ArticleOrder::Score, ArticleOrder::Time] {
+            store
+                .get_group_articles_page(&group, order, 10, None)
+                .await
+                .unwrap();
+        }
+        assert!(listed(&store, "trending:", &article).await);
+        for cache in group_cache_keys(&group) {
+            assert!(listed(&store, &cache, &article).await);
+        }
+
+        assert!(store
+            .hide_article(article_id, "moderator", "spam")
+            .await
+            .unwrap());
+        assert!(!store
+            .hide_article(article_id, "moderator", "spam")
+            .await
+            .unwrap());
+        // Dropped vote counters don't bring it back to trending
+        store.refresh_trending().await.unwrap();
+        for key in ["score:", "time:", "archive:", "trending:"] {
+            assert!(!listed(&store, key, &article).await, "{key}");
+        }
+        for cache in group_cache_keys(&group) {
+            assert!(!listed(&store, &cache, &article).await, "{cache}");
+        }
+        assert!(listed(&store, "hidden:", &article).await);
+
+        assert!(store.unhide_article(article_id).await.unwrap());
+        assert!(!store.unhide_article(article_id).await.unwrap());
+        assert!(listed(&store, "score:", &article).await);
+        assert!(listed(&store, "time:", &article).await);
+        assert!(!listed(&store, "archive:", &article).await);
+        assert!(!listed(&store, "hidden:", &article).await);
+        let article = store.get_article(article_id).await.unwrap().unwrap();
+        assert_eq!(article.upvotes, 1);
+    }
+
+    #[tokio::test]
+    async fn old_article_is_unhidden_to_archive() {
+        let store = ArticleStore::new(init_redis_client().await);
+        let article_id = store
+            .post_article(
+                "unhide-author",
+                "unhide",
+                &crate::unique_link("unhide.com"),
+            )
+            .await
+            .unwrap();
+        let article = Article::key(article_id);
+        assert!(store
+            .hide_article(article_id, "moderator", "spam")
+            .await
+            .unwrap());
+
+        // Pretend the article was posted 8 days ago
+        let posted = get_sys_time_in_secs()
+            - ONE_WEEK_IN_SECONDS as u64
+            - SECONDS_IN_DAY as u64;
+        store
+            .client()
+            .hset::<(), _, _>(&article, ("time", posted))
+            .await
+            .unwrap();
+        assert!(store.unhide_article(article_id).await.unwrap());
+
+        assert!(!listed(&store, "score:", &article).await);
+        assert!(!listed(&store, "time:", &article).await);
+        let archived: Option<f64> =
+            store.client().zscore("archive:", &article).await.unwrap();
+        assert_eq!(archived, Some(posted as f64));
+    }
+
+    #[tokio::test]
+    async fn each_user_flags_article_once() {
+        let store = ArticleStore::new(init_redis_client().await);
+        let article_id = store
+            .post_article(
+                "flag-author",
+                "flag",
+                &crate::unique_link("flag.com"),
+            )
+            .await
+            .unwrap();
+        let article = Article::key(article_id);
+
+        assert!(store.flag_article("flagger", article_id).await.unwrap());
+        assert!(!store.flag_article("flagger", article_id).await.unwrap());
+        let flags: f64 =
+            store.client().zscore("flags:", &article).await.unwrap();
+        assert_eq!(flags, 1.);
+
+        assert!(store
+            .flag_article("other-flagger", article_id)
+            .await
+            .unwrap());
+        let flags: f64 =
+            store.client().zscore("flags:", &article).await.unwrap();
+        assert_eq!(flags, 2.);
+    }
+}
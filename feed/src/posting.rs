@@ -0,0 +1,328 @@
+use fred::error::RedisError;
+use fred::interfaces::{
+    HashesInterface, KeysInterface, LuaInterface, SetsInterface,
+    SortedSetsInterface,
+};
+
+use crate::comments::{comments_key, Comment};
+use crate::editing::revisions_key;
+use crate::groups::{article_groups_key, group_cache_keys};
+use crate::link::normalize_link;
+use crate::listing::ArticleOrder;
+use crate::rate_limit::Action;
+use crate::trending::velocity_key;
+use crate::users::submitted_key;
+use crate::voting::{voted_key, VoteTarget};
+use crate::{
+    get_sys_time_in_secs, Article, ArticleStore, FeedError, ONE_WEEK_IN_SECONDS,
+};
+
+impl ArticleStore {
+    /// This function posts a new article, adds hset with article information,
+    /// then add article to the `time:` and `score` zsets.
+    /// Initial score is given by the store scoring policy.
+    /// Article is recorded in the `submitted:{user}` zset, and the
+    /// `user:{user}` profile is created on the first post.
+    /// If the same link (after `normalize_link`) was posted within the
+    /// store duplicate window, id of that article is returned instead.
+    /// `FeedEvent::Posted` is published to the global `events:` channel.
+    /// Fails with `FeedError::Banned` if user is banned and with
+    /// `FeedError::Throttled` if user posts too often.
+    pub async fn post_article(
+        &self,
+        user: &str,
+        title: &str,
+        link: &str,
+    ) -> Result<u32, FeedError> {
+        let (article_id, _) = self.submit_article(user, title, link).await?;
+        Ok(article_id)
+    }
+
+    /// Same as `post_article`, but also tells whether the article was
+    /// created (`true`) or the id of a duplicate was returned (`false`).
+    pub async fn submit_article(
+        &self,
+        user: &str,
+        title: &str,
+        link: &str,
+    ) -> Result<(u32, bool), FeedError> {
+        self.ensure_not_banned(user).await?;
+        self.check_rate_limit(Action::Post, user).await?;
+
+        let client = &self.client;
+        let now = get_sys_time_in_secs();
+        let (article_id, created): (u32, bool) = client
+            .eval(
+                POST_ARTICLE_LUA,
+                vec!["link:"],
+                vec![
+                    normalize_link(link),
+                    self.duplicate_window.as_secs().to_string(),
+                    now.to_string(),
+                    title.to_string(),
+                    link.to_string(),
+                    user.to_string(),
+                ],
+            )
+            .await?;
+        if !created {
+            return Ok((article_id, false));
+        }
+
+        let voted = format!("upvoted:{article_id}");
+        client.sadd::<(), _, _>(&voted, user).await?;
+        client.expire::<(), _>(voted, ONE_WEEK_IN_SECONDS).await?;
+
+        let article = Article::key(article_id);
+        client
+            .zadd::<(), _, _>(
+                "score:",
+                None,
+                None,
+                false,
+                false,
+                vec![(self.scoring.score(now, 1, 0, now), &article)],
+            )
+            .await?;
+        client
+            .zadd::<(), _, _>(
+                "time:",
+                None,
+                None,
+                false,
+                false,
+                vec![(now as f64, &article)],
+            )
+            .await?;
+        self.record_submission(user, &article, now).await?;
+        self.reindex_article(article_id, None, Some(title)).await?;
+
+        let posted = Article {
+            id: article_id,
+            title: title.to_string(),
+            link: link.to_string(),
+            author: user.to_string(),
+            time: now,
+            upvotes: 1,
+            downvotes: 0,
+            comments: 0,
+            score: None,
+            hidden: None,
+            edited: None,
+        };
+        self.publish_posted(&posted, &[]).await?;
+
+        Ok((article_id, true))
+    }
+
+    /// Delete article with all its data: hash, revisions, vote sets and
+    /// counters, flags, entries in the `time:`, `score:`, `archive:`,
+    /// trending and moderation zsets, membership in the groups of its
+    /// `groups:{article_id}` set (with cached group listings), title words
+    /// index, and all article comments.
+    pub async fn delete_article(
+        &self,
+        article_id: u32,
+    ) -> Result<(), RedisError> {
+        let client = &self.client;
+        let article = Article::key(article_id);
+
+        let groups: Vec<String> =
+            client.smembers(article_groups_key(article_id)).await?;
+        let (author, link, title): (
+            Option<String>,
+            Option<String>,
+            Option<String>,
+        ) = client
+            .hmget(&article, vec!["author", "link", "title"])
+            .await?;
+        let link = link.map(|link| normalize_link(&link));
+        let indexed: Option<u32> = match &link {
+            Some(link) => client.hget("link:", link).await?,
+            None => None,
+        };
+        let upvoters: Vec<String> =
+            client.smembers(format!("upvoted:{article_id}")).await?;
+
+        let comments_by_time = comments_key(ArticleOrder::Time, article_id);
+        let comments: Vec<String> = client
+            .zrange(&comments_by_time, 0, -1, None, false, None, false)
+            .await?;
+
+        let pipe = client.pipeline();
+        for comment in comments.iter() {
+            if let Some(comment_id) = Comment::id_from_key(comment) {
+                let target = VoteTarget::comment(comment_id, article_id);
+                pipe.del::<(), _>(vec![
+                    target.hash,
+                    target.upvoted,
+                    target.downvoted,
+                ])
+                .await?;
+            }
+        }
+        for group in groups.iter() {
+            pipe.srem::<(), _, _>(format!("group:{group}"), &article)
+                .await?;
+            for cache in group_cache_keys(group) {
+                pipe.zrem::<(), _, _>(cache, &article).await?;
+            }
+        }
+        pipe.zrem::<(), _, _>("score:", &article).await?;
+        pipe.zrem::<(), _, _>("time:", &article).await?;
+        pipe.zrem::<(), _, _>("archive:", &article).await?;
+        pipe.zrem::<(), _, _>("votes:", &article).await?;
+        pipe.zrem::<(), _, _>("trending:", &article).await?;
+        pipe.zrem::<(), _, _>("flags:", &article).await?;
+        pipe.zrem::<(), _, _>("hidden:", &article).await?;
+        if let Some(author) = author {
+            pipe.zrem::<(), _, _>(submitted_key(&author), &article)
+                .await?;
+        }
+        if let (Some(link), Some(id)) = (link, indexed) {
+            // Link may be reposted after the window, keep the newer entry
+            if id == article_id {
+                pipe.hdel::<(), _, _>("link:", link).await?;
+            }
+        }
+        for user in upvoters.iter() {
+            pipe.zrem::<(), _, _>(voted_key(user), &article).await?;
+        }
+        pipe.del::<(), _>(vec![
+            article,
+            format!("upvoted:{article_id}"),
+            format!("downvoted:{article_id}"),
+            velocity_key(article_id),
+            format!("flagged:{article_id}"),
+            revisions_key(article_id),
+            article_groups_key(article_id),
+            comments_by_time,
+            comments_key(ArticleOrder::Score, article_id),
+        ])
+        .await?;
+        pipe.all::<()>().await?;
+
+        self.reindex_article(article_id, title.as_deref(), None)
+            .await
+    }
+}
+
+/// Look up normalized link `ARGV[1]` in the `link:` hash `KEYS[1]`.
+/// If it points to an article posted less than `ARGV[2]` seconds before
+/// `ARGV[3]`, returns `{article_id, 0}`. Otherwise allocates new article
+/// id, creates the article hash with title `ARGV[4]`, link `ARGV[5]` and
+/// author `ARGV[6]`, indexes the link and returns `{article_id, 1}`.
+const POST_ARTICLE_LUA: &str = r#"
+local window = tonumber(ARGV[2])
+local now = tonumber(ARGV[3])
+if window > 0 then
+    local existing = redis.call('HGET', KEYS[1], ARGV[1])
+    if existing then
+        local time = redis.call('HGET', 'article:' .. existing, 'time')
+        if time and tonumber(time) >= now - window then
+            return {tonumber(existing), 0}
+        end
+    end
+end
+local id = redis.call('INCR', 'article:')
+redis.call('HSET', 'article:' .. id,
+    'title', ARGV[4],
+    'link', ARGV[5],
+    'author', ARGV[6],
+    'time', ARGV[3],
+    'upvotes', 1,
+    'downvotes', 0)
+redis.call('HSET', KEYS[1], ARGV[1], id)
+return {id, 1}
+"#;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::init_redis_client;
+    use crate::search::index_key;
+
+    #[tokio::test]
+    async fn deleted_article_leaves_no_keys() {
+        let store = ArticleStore::new(init_redis_client().await);
+        let client = store.client();
+        let link = crate::unique_link("cleanup.com");
+        let word = format!("cleanup{}", link.rsplit('/').next().unwrap());
+        let article_id = store
+            .post_article("cleanup-author", &format!("The {word}"), &link)
+            .await
+            .unwrap();
+        let article = Article::key(article_id);
+        let group = format!("cleanup{article_id}");
+        store
+            .add_remove_groups(article_id, &[group.as_str()], &[])
+            .await
+            .unwrap();
+        store.get_group_articles_by_score(&group, 1).await.unwrap();
+        store
+            .article_vote("cleanup-voter", article_id, true)
+            .await
+            .unwrap();
+        let comment_id = store
+            .post_comment("cleanup-commenter", article_id, None, "First")
+            .await
+            .unwrap();
+        let reply_id = store
+            .post_comment("cleanup-voter", article_id, Some(comment_id), "Re")
+            .await
+            .unwrap();
+        store
+            .comment_vote("cleanup-voter", comment_id, false)
+            .await
+            .unwrap();
+
+        store.delete_article(article_id).await.unwrap();
+
+        let mut keys = vec![
+            article.clone(),
+            format!("upvoted:{article_id}"),
+            format!("downvoted:{article_id}"),
+            velocity_key(article_id),
+            format!("flagged:{article_id}"),
+            revisions_key(article_id),
+            article_groups_key(article_id),
+            comments_key(ArticleOrder::Time, article_id),
+            comments_key(ArticleOrder::Score, article_id),
+            index_key(&word),
+        ];
+        for comment_id in [comment_id, reply_id] {
+            let target = VoteTarget::comment(comment_id, article_id);
+            keys.extend([target.hash, target.upvoted, target.downvoted]);
+        }
+        for key in keys {
+            let exists: bool = client.exists(&key).await.unwrap();
+            assert!(!exists, "{key} is left");
+        }
+
+        let mut zsets = vec![
+            "score:".to_string(),
+            "time:".to_string(),
+            "archive:".to_string(),
+            "votes:".to_string(),
+            "trending:".to_string(),
+            "flags:".to_string(),
+            "hidden:".to_string(),
+            submitted_key("cleanup-author"),
+            voted_key("cleanup-voter"),
+        ];
+        zsets.extend(group_cache_keys(&group));
+        for zset in zsets {
+            let score: Option<f64> =
+                client.zscore(&zset, &article).await.unwrap();
+            assert_eq!(score, None, "{article} is left in {zset}");
+        }
+        let member: bool = client
+            .sismember(format!("group:{group}"), &article)
+            .await
+            .unwrap();
+        assert!(!member);
+        let indexed: Option<u32> =
+            client.hget("link:", normalize_link(&link)).await.unwrap();
+        assert_eq!(indexed, None);
+    }
+}
@@ -0,0 +1,128 @@
+use std::time::Duration;
+
+use fred::interfaces::LuaInterface;
+
+use crate::{ArticleStore, FeedError};
+
+/// Rate limited action.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Action {
+    Post,
+    Vote,
+}
+
+impl Action {
+    fn as_str(&self) -> &'static str {
+        match self {
+            Action::Post => "post",
+            Action::Vote => "vote",
+        }
+    }
+}
+
+/// At most `limit` actions during any `window`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RateLimit {
+    pub limit: u64,
+    pub window: Duration,
+}
+
+impl RateLimit {
+    pub fn new(limit: u64, window: Duration) -> Self {
+        RateLimit { limit, window }
+    }
+}
+
+/// Limits applied to posting and voting, `None` means no limit.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct RateLimits {
+    pub post_per_user: Option<RateLimit>,
+    pub post_per_ip: Option<RateLimit>,
+    pub vote_per_user: Option<RateLimit>,
+    pub vote_per_ip: Option<RateLimit>,
+}
+
+impl RateLimits {
+    fn for_action(
+        &self,
+        action: Action,
+    ) -> (Option<RateLimit>, Option<RateLimit>) {
+        match action {
+            Action::Post => (self.post_per_user, self.post_per_ip),
+            Action::Vote => (self.vote_per_user, self.vote_per_ip),
+        }
+    }
+}
+
+impl ArticleStore {
+    /// Record the action of the user (and store IP, if set with `with_ip`)
+    /// in the `rate:{action}:user:{user}` and `rate:{action}:ip:{ip}`
+    /// sliding windows.
+    /// Fails with `FeedError::Throttled` if any window is full, in that case
+    /// action is not recorded.
+    pub async fn check_rate_limit(
+        &self,
+        action: Action,
+        user: &str,
+    ) -> Result<(), FeedError> {
+        let (per_user, per_ip) = self.rate_limits.for_action(action);
+        let mut keys = Vec::new();
+        let mut args = Vec::new();
+        let mut push = |key: String, rate: RateLimit| {
+            keys.push(key);
+            args.push(rate.limit.to_string());
+            args.push(rate.window.as_millis().to_string());
+        };
+        if let Some(rate) = per_user {
+            push(format!("rate:{}:user:{user}", action.as_str()), rate);
+        }
+        if let (Some(rate), Some(ip)) = (per_ip, self.ip.as_deref()) {
+            push(format!("rate:{}:ip:{ip}", action.as_str()), rate);
+        }
+        if keys.is_empty() {
+            return Ok(());
+        }
+
+        let retry_after: u64 =
+            self.client.eval(RATE_LIMIT_LUA, keys, args).await?;
+        if retry_after > 0 {
+            return Err(FeedError::Throttled {
+                retry_after: Duration::from_millis(retry_after),
+            });
+        }
+        Ok(())
+    }
+}
+
+/// Sliding window rate limiter over zsets `KEYS`, members are
+/// requests scored by time in milliseconds. For each key `ARGV` contains
+/// pair of limit and window length in milliseconds.
+/// If all windows have free space, request is recorded in all of them
+/// and 0 is returned, otherwise returns milliseconds to wait.
+const RATE_LIMIT_LUA: &str = r#"
+local time = redis.call('TIME')
+local now = tonumber(time[1]) * 1000 + math.floor(tonumber(time[2]) / 1000)
+local wait = 0
+for i, key in ipairs(KEYS) do
+    local limit = tonumber(ARGV[i * 2 - 1])
+    local window = tonumber(ARGV[i * 2])
+    redis.call('ZREMRANGEBYSCORE', key, '-inf', now - window)
+    if redis.call('ZCARD', key) >= limit then
+        local oldest = redis.call('ZRANGE', key, 0, 0, 'WITHSCORES')
+        local key_wait = 1
+        if oldest[2] then
+            key_wait = math.max(tonumber(oldest[2]) + window - now, 1)
+        end
+        wait = math.max(wait, key_wait)
+    end
+end
+if wait > 0 then
+    return wait
+end
+local member = now .. '-' .. redis.call('INCR', 'rate:seq')
+for i, key in ipairs(KEYS) do
+    redis.call('ZADD', key, now, member)
+    redis.call('PEXPIRE', key, ARGV[i * 2])
+end
+return 0
+"#;
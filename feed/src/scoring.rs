@@ -0,0 +1,398 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use fred::error::{RedisError, RedisErrorKind};
+use fred::interfaces::{
+    HashesInterface, KeysInterface, LuaInterface, SortedSetsInterface,
+};
+
+use crate::{get_sys_time_in_secs, ArticleStore, RATIO};
+
+/// How many articles are rescored per one `zrange` call.
+const RECOMPUTE_BATCH: i64 = 100;
+
+/// String key with the name of the built-in policy `score:` is built with.
+const SCORING_KEY: &str = "scoring:";
+
+/// Algorithm used to rank articles in the `score:` zset.
+///
+/// Score is computed from the article post time and its vote counters,
+/// the higher score is, the closer article is to the top of the front page.
+pub trait ScoringPolicy: Send + Sync {
+    /// Score of the article posted at `time` with given vote counters,
+    /// at the moment `now`.
+    fn score(&self, time: u64, upvotes: i64, downvotes: i64, now: u64) -> f64;
+
+    /// Scores change with time alone, so `recompute_scores` should be
+    /// called periodically.
+    fn needs_recompute(&self) -> bool {
+        false
+    }
+}
+
+/// Built-in policy with default parameters by its name:
+/// `linear`, `gravity` or `wilson`.
+pub fn policy_by_name(name: &str) -> Option<Arc<dyn ScoringPolicy>> {
+    match name {
+        "linear" => Some(Arc::new(LinearDecay::default())),
+        "gravity" => Some(Arc::new(Gravity::default())),
+        "wilson" => Some(Arc::new(Wilson::default())),
+        _ => None,
+    }
+}
+
+/// Default policy: each upvote moves article half an hour forward
+/// in time, each downvote moves it back, so old articles decay linearly.
+#[derive(Debug, Clone, Copy)]
+pub struct LinearDecay {
+    /// Seconds added per vote.
+    pub ratio: f64,
+}
+
+impl Default for LinearDecay {
+    fn default() -> Self {
+        LinearDecay {
+            ratio: RATIO as f64,
+        }
+    }
+}
+
+impl ScoringPolicy for LinearDecay {
+    fn score(&self, time: u64, upvotes: i64, downvotes: i64, _now: u64) -> f64 {
+        time as f64 + self.ratio * (upvotes - downvotes) as f64
+    }
+}
+
+/// Hacker News like policy: `(points - 1) / (age_in_hours + 2) ^ gravity`.
+///
+/// Score depends on the current time, so `recompute_scores` should be
+/// called periodically to keep the `score:` zset fresh.
+#[derive(Debug, Clone, Copy)]
+pub struct Gravity {
+    pub gravity: f64,
+}
+
+impl Default for Gravity {
+    fn default() -> Self {
+        Gravity { gravity: 1.8 }
+    }
+}
+
+impl ScoringPolicy for Gravity {
+    fn score(&self, time: u64, upvotes: i64, downvotes: i64, now: u64) -> f64 {
+        let points = (upvotes - downvotes) as f64;
+        let age_in_hours = now.saturating_sub(time) as f64 / 3600.;
+        (points - 1.) / (age_in_hours + 2.).powf(self.gravity)
+    }
+
+    fn needs_recompute(&self) -> bool {
+        true
+    }
+}
+
+/// Lower bound of Wilson score confidence interval for the share
+/// of upvotes, ignores article age.
+#[derive(Debug, Clone, Copy)]
+pub struct Wilson {
+    /// Quantile of the standard normal distribution,
+    /// 1.96 for 95% confidence.
+    pub z: f64,
+}
+
+impl Default for Wilson {
+    fn default() -> Self {
+        Wilson { z: 1.96 }
+    }
+}
+
+impl ScoringPolicy for Wilson {
+    fn score(
+        &self,
+        _time: u64,
+        upvotes: i64,
+        downvotes: i64,
+        _now: u64,
+    ) -> f64 {
+        let n = (upvotes + downvotes) as f64;
+        if n <= 0. {
+            return 0.;
+        }
+        let p = upvotes as f64 / n;
+        let z2 = self.z * self.z;
+        (p + z2 / (2. * n)
+            - self.z * ((p * (1. - p) + z2 / (4. * n)) / n).sqrt())
+            / (1. + z2 / n)
+    }
+}
+
+impl ArticleStore {
+    /// Use the built-in policy saved by `save_scoring`, so all processes
+    /// writing to `score:` agree on it. Store without saved policy keeps
+    /// its current one.
+    pub async fn with_saved_scoring(mut self) -> Result<Self, RedisError> {
+        let name: Option<String> = self.client.get(SCORING_KEY).await?;
+        if let Some(name) = name {
+            self.scoring = policy_by_name(&name).ok_or_else(|| {
+                RedisError::new(
+                    RedisErrorKind::InvalidArgument,
+                    format!("Unknown scoring policy: {name}"),
+                )
+            })?;
+        }
+        Ok(self)
+    }
+
+    /// Switch the store to the built-in policy and save its name in the
+    /// `scoring:` key for `with_saved_scoring`. Call `recompute_scores`
+    /// afterwards, and restart processes which loaded the old policy.
+    pub async fn save_scoring(
+        mut self,
+        name: &str,
+    ) -> Result<Self, RedisError> {
+        self.scoring = policy_by_name(name).ok_or_else(|| {
+            RedisError::new(
+                RedisErrorKind::InvalidArgument,
+                format!("Unknown scoring policy: {name}"),
+            )
+        })?;
+        self.client
+            .set::<(), _, _>(SCORING_KEY, name, None, None, false)
+            .await?;
+        Ok(self)
+    }
+
+    /// This task should run in background, it recomputes scores every
+    /// `interval` if the policy needs it, and returns at once otherwise.
+    /// Errors are logged, and recompute is retried after the next interval.
+    pub async fn recompute_task(&self, interval: Duration) {
+        if !self.scoring.needs_recompute() {
+            return;
+        }
+        loop {
+            if let Err(e) = self.recompute_scores().await {
+                eprintln!("Score recompute failed: {e}");
+            }
+            tokio::time::sleep(interval).await;
+        }
+    }
+
+    /// Recompute score of every live article (member of `time:` zset)
+    /// with the current scoring policy.
+    /// Should be called when the policy changes, or periodically for
+    /// policies which depend on the current time.
+    /// Returns the number of rescored articles.
+    pub async fn recompute_scores(&self) -> Result<usize, RedisError> {
+        let mut start = 0;
+        let mut rescored = 0;
+        loop {
+            let keys: Vec<String> = self
+                .client
+                .zrange(
+                    "time:",
+                    start,
+                    start + RECOMPUTE_BATCH - 1,
+                    None,
+                    false,
+                    None,
+                    false,
+                )
+                .await?;
+            if keys.is_empty() {
+                return Ok(rescored);
+            }
+            for key in keys.iter() {
+                self.update_score(key, "score:").await?;
+            }
+            rescored += keys.len();
+            start += RECOMPUTE_BATCH;
+        }
+    }
+
+    /// Write score of the article (or comment) to the `score_zset`,
+    /// computing it from the current hash. Items which are not in the
+    /// zset (archived articles) are left untouched.
+    ///
+    /// Score is written only if counters didn't change since they were
+    /// read, otherwise the concurrent writer, who changed them, is
+    /// responsible for the update. So a stale score can't overwrite
+    /// a fresh one. Returns the counters and score which were written,
+    /// `None` if the item is gone or was changed concurrently.
+    pub(crate) async fn update_score(
+        &self,
+        hash: &str,
+        score_zset: &str,
+    ) -> Result<Option<Scored>, RedisError> {
+        let fields: (Option<u64>, Option<i64>, Option<i64>) = self
+            .client
+            .hmget(hash, vec!["time", "upvotes", "downvotes"])
+            .await?;
+        let (Some(time), Some(upvotes), Some(downvotes)) = fields else {
+            return Ok(None);
+        };
+        let score = self.scoring.score(
+            time,
+            upvotes,
+            downvotes,
+            get_sys_time_in_secs(),
+        );
+        let written: i64 = self
+            .client
+            .eval(
+                SET_SCORE_LUA,
+                vec![hash, score_zset],
+                vec![
+                    upvotes.to_string(),
+                    downvotes.to_string(),
+                    score.to_string(),
+                ],
+            )
+            .await?;
+        Ok((written >= 0).then_some(Scored {
+            upvotes,
+            downvotes,
+            score: (written == 1).then_some(score),
+        }))
+    }
+}
+
+/// Vote counters of the item and its score written by `update_score`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) struct Scored {
+    pub(crate) upvotes: i64,
+    pub(crate) downvotes: i64,
+    /// `None` if the item is not in the score zset.
+    pub(crate) score: Option<f64>,
+}
+
+/// Set score `ARGV[3]` of item `KEYS[1]` in the `KEYS[2]` zset, if
+/// item is still there and its counters are equal to `ARGV[1]` upvotes
+/// and `ARGV[2]` downvotes.
+/// Returns 1 if score was set, 0 if item is not in the zset and -1 if
+/// counters were changed.
+const SET_SCORE_LUA: &str = r#"
+local counters = redis.call('HMGET', KEYS[1], 'upvotes', 'downvotes')
+if counters[1] ~= ARGV[1] or counters[2] ~= ARGV[2] then
+    return -1
+end
+if not redis.call('ZSCORE', KEYS[2], KEYS[1]) then
+    return 0
+end
+redis.call('ZADD', KEYS[2], ARGV[3], KEYS[1])
+return 1
+"#;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{init_redis_client, Article, SECONDS_IN_DAY};
+
+    #[test]
+    fn linear_decay_matches_ratio() {
+        let policy = LinearDecay::default();
+        assert_eq!(policy.score(1000, 1, 0, 0), 1000. + RATIO as f64);
+        assert_eq!(policy.score(1000, 3, 1, 0), 1000. + 2. * RATIO as f64);
+    }
+
+    #[test]
+    fn gravity_decays_with_age() {
+        let policy = Gravity::default();
+        let fresh = policy.score(0, 10, 0, 0);
+        let old = policy.score(0, 10, 0, 24 * 3600);
+        assert!(fresh > old);
+    }
+
+    #[test]
+    fn only_gravity_needs_recompute() {
+        let needs = |name| policy_by_name(name).unwrap().needs_recompute();
+        assert!(!needs("linear"));
+        assert!(needs("gravity"));
+        assert!(!needs("wilson"));
+        assert!(policy_by_name("random").is_none());
+    }
+
+    #[test]
+    fn wilson_prefers_more_votes_with_same_ratio() {
+        let policy = Wilson::default();
+        assert_eq!(policy.score(0, 0, 0, 0), 0.);
+        let few = policy.score(0, 9, 1, 0);
+        let many = policy.score(0, 900, 100, 0);
+        assert!(many > few);
+        assert!(many < 0.9);
+    }
+
+    #[tokio::test]
+    async fn recompute_rescores_only_live_articles() {
+        let store = ArticleStore::new(init_redis_client().await);
+        let client = store.client();
+        let mut ids = Vec::new();
+        for i in 0..2 {
+            let id = store
+                .post_article(
+                    &format!("recompute-author-{i}"),
+                    "recompute",
+                    &crate::unique_link("recompute.com"),
+                )
+                .await
+                .unwrap();
+            ids.push(id);
+        }
+        let (live, archived) = (Article::key(ids[0]), Article::key(ids[1]));
+
+        // Second article is archived with its frozen score
+        let posted = get_sys_time_in_secs() - 8 * SECONDS_IN_DAY as u64;
+        client
+            .zadd::<(), _, _>(
+                "time:",
+                None,
+                None,
+                false,
+                false,
+                (posted as f64, &archived),
+            )
+            .await
+            .unwrap();
+        store.archive_articles().await.unwrap();
+        let frozen: f64 = client.hget(&archived, "score").await.unwrap();
+
+        // Live article keeps the score of the previous policy
+        let (time, upvotes, downvotes): (u64, i64, i64) = client
+            .hmget(&live, vec!["time", "upvotes", "downvotes"])
+            .await
+            .unwrap();
+        let previous = Wilson::default().score(time, upvotes, downvotes, 0);
+        client
+            .zadd::<(), _, _>(
+                "score:",
+                None,
+                None,
+                false,
+                false,
+                (previous, &live),
+            )
+            .await
+            .unwrap();
+
+        assert!(store.recompute_scores().await.unwrap() >= 1);
+
+        let score: f64 = client.zscore("score:", &live).await.unwrap();
+        assert_eq!(
+            score,
+            LinearDecay::default().score(time, upvotes, downvotes, 0)
+        );
+        let rescored: Option<f64> =
+            client.zscore("score:", &archived).await.unwrap();
+        assert_eq!(rescored, None);
+        let still_frozen: f64 = client.hget(&archived, "score").await.unwrap();
+        assert_eq!(still_frozen, frozen);
+        let archive: Option<f64> =
+            client.zscore("archive:", &archived).await.unwrap();
+        assert_eq!(archive, Some(posted as f64));
+    }
+
+    #[tokio::test]
+    async fn unknown_policy_is_not_saved() {
+        let store = ArticleStore::new(init_redis_client().await);
+        let error = store.save_scoring("random").await.err().unwrap();
+        assert_eq!(*error.kind(), RedisErrorKind::InvalidArgument);
+    }
+}
@@ -0,0 +1,267 @@
+use fred::error::RedisError;
+use fred::interfaces::SetsInterface;
+
+use crate::listing::{ArticleOrder, Cursor, Page};
+use crate::{Article, ArticleStore};
+
+/// Words which are too common to be indexed.
+const STOP_WORDS: &[&str] = &[
+    "a", "about", "an", "and", "are", "as", "at", "be", "but", "by", "for",
+    "from", "has", "have", "how", "i", "in", "is", "it", "its", "of", "on",
+    "or", "that", "the", "this", "to", "was", "what", "when", "where", "who",
+    "why", "will", "with", "you",
+];
+
+/// Split title into lowercased words, dropping punctuation, stop words
+/// and duplicates. Same tokenizer is used for indexing and for queries.
+pub fn tokenize(text: &str) -> Vec<String> {
+    let mut words = Vec::new();
+    for word in text.split(|c: char| !c.is_alphanumeric()) {
+        let word = word.to_lowercase();
+        if word.is_empty()
+            || STOP_WORDS.contains(&word.as_str())
+            || words.contains(&word)
+        {
+            continue;
+        }
+        words.push(word);
+    }
+    words
+}
+
+/// Set of articles with the word in the title, `idx:{word}`.
+pub(crate) fn index_key(word: &str) -> String {
+    format!("idx:{word}")
+}
+
+/// Full-text query over article titles.
+///
+/// Parsed from a string where plain words must all be present,
+/// words joined with `OR` need at least one to be present,
+/// and words prefixed with `-` or `NOT` must be absent:
+/// `redis OR postgres -mysql` finds titles with "redis" or "postgres"
+/// which don't mention "mysql".
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SearchQuery {
+    /// Title should contain at least one of these words.
+    pub any: Vec<String>,
+    /// Title should contain each of these words.
+    pub all: Vec<String>,
+    /// Title should contain none of these words.
+    pub none: Vec<String>,
+}
+
+impl SearchQuery {
+    pub fn parse(query: &str) -> Self {
+        let mut terms = Vec::new();
+        let mut negate = false;
+        let mut join = false;
+        for term in query.split_whitespace() {
+            match term {
+                "NOT" => negate = true,
+                "OR" => join = true,
+                _ => {
+                    let (term, excluded) = match term.strip_prefix('-') {
+                        Some(term) => (term, true),
+                        None => (term, negate),
+                    };
+                    for word in tokenize(term) {
+                        terms.push((word, excluded, join));
+                    }
+                    negate = false;
+                    join = false;
+                }
+            }
+        }
+
+        // Word joins the `any` list if it is on either side of `OR`
+        let mut query = SearchQuery::default();
+        for (i, (word, excluded, joined)) in terms.iter().enumerate() {
+            let next_joined = terms.get(i + 1).is_some_and(|next| next.2);
+            let list = if *excluded {
+                &mut query.none
+            } else if *joined || next_joined {
+                &mut query.any
+            } else {
+                &mut query.all
+            };
+            if !list.contains(word) {
+                list.push(word.clone());
+            }
+        }
+        query
+    }
+
+    /// Query has no words to look for, only exclusions.
+    pub fn is_empty(&self) -> bool {
+        self.any.is_empty() && self.all.is_empty()
+    }
+
+    /// Canonical form of the query, used as a part of the cache key.
+    fn canonical(&self) -> String {
+        fn list(words: &[String]) -> String {
+            let mut words = words.to_vec();
+            words.sort();
+            words.join(",")
+        }
+        format!(
+            "any={};all={};none={}",
+            list(&self.any),
+            list(&self.all),
+            list(&self.none)
+        )
+    }
+}
+
+impl ArticleStore {
+    /// Cursor-based listing of articles matching the query, ordered by
+    /// `order`. Archived articles are found too: by post time in the time
+    /// order, and after the articles with positive score in the score
+    /// order. Result is cached in the `cache:score:search:{query}` or
+    /// `cache:time:search:{query}` zset for 1 minute. Query without words
+    /// to look for gives an empty page.
+    pub async fn search_articles(
+        &self,
+        query: &SearchQuery,
+        order: ArticleOrder,
+        page_size: usize,
+        cursor: Option<&Cursor>,
+    ) -> Result<Page, RedisError> {
+        if query.is_empty() {
+            return Ok(Page {
+                articles: Vec::new(),
+                next: None,
+            });
+        }
+        let index_keys = |words: &[String]| {
+            words.iter().map(|word| index_key(word)).collect::<Vec<_>>()
+        };
+        // Archived articles are scored by post time, in score order
+        // they are weighted with 0 and follow the live ones
+        let archive_weight = match order {
+            ArticleOrder::Score => 0.,
+            ArticleOrder::Time => 1.,
+        };
+        let destination = self
+            .cache_set_query(
+                format!("cache:{}search:{}", order.key(), query.canonical()),
+                &[(order.key(), 1.), ("archive:", archive_weight)],
+                index_keys(&query.any),
+                index_keys(&query.all),
+                index_keys(&query.none),
+            )
+            .await?;
+        self.get_page(&destination, page_size, cursor).await
+    }
+
+    /// Update `idx:{word}` sets after article title changed from
+    /// `old_title` to `new_title`. `None` title means that article
+    /// is created or deleted.
+    pub(crate) async fn reindex_article(
+        &self,
+        article_id: u32,
+        old_title: Option<&str>,
+        new_title: Option<&str>,
+    ) -> Result<(), RedisError> {
+        let old_words = old_title.map(tokenize).unwrap_or_default();
+        let new_words = new_title.map(tokenize).unwrap_or_default();
+        let article = Article::key(article_id);
+
+        let pipe = self.client.pipeline();
+        let mut changed = false;
+        for word in old_words.iter().filter(|w| !new_words.contains(w)) {
+            pipe.srem::<(), _, _>(index_key(word), &article).await?;
+            changed = true;
+        }
+        for word in new_words.iter().filter(|w| !old_words.contains(w)) {
+            pipe.sadd::<(), _, _>(index_key(word), &article).await?;
+            changed = true;
+        }
+        if !changed {
+            return Ok(());
+        }
+        pipe.all::<()>().await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use fred::interfaces::SortedSetsInterface;
+
+    use super::*;
+    use crate::{get_sys_time_in_secs, init_redis_client, SECONDS_IN_DAY};
+
+    fn words(words: &[&str]) -> Vec<String> {
+        words.iter().map(|w| w.to_string()).collect()
+    }
+
+    #[test]
+    fn titles_are_tokenized_without_stop_words() {
+        assert_eq!(
+            tokenize("The Rust book: how to write a Redis client, in Rust!"),
+            words(&["rust", "book", "write", "redis", "client"])
+        );
+        assert_eq!(tokenize("  --- "), Vec::<String>::new());
+    }
+
+    #[test]
+    fn query_operators_are_parsed() {
+        let query = SearchQuery::parse("rust redis OR Postgres -mysql NOT the");
+        assert_eq!(query.all, words(&["rust"]));
+        assert_eq!(query.any, words(&["redis", "postgres"]));
+        assert_eq!(query.none, words(&["mysql"]));
+
+        let query = SearchQuery::parse("x OR y OR z NOT w");
+        assert_eq!(query.any, words(&["x", "y", "z"]));
+        assert_eq!(query.none, words(&["w"]));
+        assert!(SearchQuery::parse("-cats").is_empty());
+    }
+
+    #[tokio::test]
+    async fn archived_articles_are_found() {
+        let store = ArticleStore::new(init_redis_client().await);
+        let link = crate::unique_link("search.com");
+        let word = format!("archived{}", link.rsplit('/').next().unwrap());
+        let mut ids = Vec::new();
+        for i in 0..2 {
+            let id = store
+                .post_article(
+                    &format!("search-author-{i}"),
+                    &format!("The {word}"),
+                    &format!("{link}/{i}"),
+                )
+                .await
+                .unwrap();
+            ids.push(id);
+        }
+        // Second article is posted 8 days ago and archived
+        let posted = get_sys_time_in_secs() - 8 * SECONDS_IN_DAY as u64;
+        store
+            .client()
+            .zadd::<(), _, _>(
+                "time:",
+                None,
+                None,
+                false,
+                false,
+                (posted as f64, Article::key(ids[1])),
+            )
+            .await
+            .unwrap();
+        store.archive_articles().await.unwrap();
+
+        let query = SearchQuery::parse(&word);
+        for order in [ArticleOrder::Time, ArticleOrder::Score] {
+            let page = store
+                .search_articles(&query, order, 10, None)
+                .await
+                .unwrap();
+            let found = page
+                .articles
+                .iter()
+                .map(|article| article.id)
+                .collect::<Vec<_>>();
+            assert_eq!(found, ids, "{order:?}");
+        }
+    }
+}
@@ -0,0 +1,408 @@
+use std::fmt;
+use std::future::Future;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use crate::{get_sys_time_in_secs, ArticleStore, FeedError};
+
+/// Workload of the `seed` command.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SeedConfig {
+    /// Number of distinct users, named `seed-user-{n}`.
+    pub users: usize,
+    pub articles: usize,
+    /// Articles are spread evenly over `seed-{n}` groups.
+    pub groups: usize,
+    pub votes: usize,
+    /// Exponent of the Zipf distribution of votes over articles,
+    /// 0 is uniform, around 1 is typical for popularity.
+    pub zipf_exponent: f64,
+    /// Share of upvotes among votes.
+    pub upvote_ratio: f64,
+    /// Number of concurrent workers.
+    pub concurrency: usize,
+    /// Seed of the random generator, the same seed gives the same workload.
+    pub seed: u64,
+}
+
+impl Default for SeedConfig {
+    fn default() -> Self {
+        SeedConfig {
+            users: 100,
+            articles: 1000,
+            groups: 10,
+            votes: 10_000,
+            zipf_exponent: 1.0,
+            upvote_ratio: 0.8,
+            concurrency: 16,
+            seed: 42,
+        }
+    }
+}
+
+impl SeedConfig {
+    /// Parse `--name value` options, missing options keep default values.
+    pub fn from_args(args: &[&str]) -> Result<Self, String> {
+        fn parse<T: std::str::FromStr>(
+            name: &str,
+            value: &str,
+        ) -> Result<T, String> {
+            value
+                .parse()
+                .map_err(|_| format!("Invalid value of {name}: {value}"))
+        }
+
+        let mut config = SeedConfig::default();
+        let mut args = args.iter();
+        while let Some(name) = args.next() {
+            let value = args
+                .next()
+                .ok_or_else(|| format!("Missing value of {name}"))?;
+            match *name {
+                "--users" => config.users = parse(name, value)?,
+                "--articles" => config.articles = parse(name, value)?,
+                "--groups" => config.groups = parse(name, value)?,
+                "--votes" => config.votes = parse(name, value)?,
+                "--zipf" => config.zipf_exponent = parse(name, value)?,
+                "--upvotes" => config.upvote_ratio = parse(name, value)?,
+                "--concurrency" => config.concurrency = parse(name, value)?,
+                "--seed" => config.seed = parse(name, value)?,
+                other => return Err(format!("Unknown option: {other}")),
+            }
+        }
+        if config.users == 0 || config.concurrency == 0 {
+            return Err("--users and --concurrency should be positive".into());
+        }
+        Ok(config)
+    }
+}
+
+/// Throughput and latency of one phase of the workload.
+#[derive(Debug, Clone)]
+pub struct PhaseReport {
+    pub name: &'static str,
+    pub ops: usize,
+    pub elapsed: Duration,
+    pub p50: Duration,
+    pub p90: Duration,
+    pub p99: Duration,
+    pub max: Duration,
+}
+
+impl PhaseReport {
+    fn new(
+        name: &'static str,
+        elapsed: Duration,
+        mut latencies: Vec<Duration>,
+    ) -> Self {
+        latencies.sort();
+        PhaseReport {
+            name,
+            ops: latencies.len(),
+            elapsed,
+            p50: percentile(&latencies, 0.50),
+            p90: percentile(&latencies, 0.90),
+            p99: percentile(&latencies, 0.99),
+            max: latencies.last().copied().unwrap_or_default(),
+        }
+    }
+
+    pub fn ops_per_sec(&self) -> f64 {
+        self.ops as f64 / self.elapsed.as_secs_f64().max(f64::EPSILON)
+    }
+}
+
+impl fmt::Display for PhaseReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{:<7} {:>8} ops {:>10.1} ops/sec  p50 {:>8.2?}  p90 {:>8.2?}  \
+             p99 {:>8.2?}  max {:>8.2?}",
+            self.name,
+            self.ops,
+            self.ops_per_sec(),
+            self.p50,
+            self.p90,
+            self.p99,
+            self.max
+        )
+    }
+}
+
+/// Reports of all phases, in order of execution.
+#[derive(Debug, Clone)]
+pub struct SeedReport {
+    pub phases: Vec<PhaseReport>,
+}
+
+impl fmt::Display for SeedReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for phase in self.phases.iter() {
+            writeln!(f, "{phase}")?;
+        }
+        Ok(())
+    }
+}
+
+impl ArticleStore {
+    /// Populate the store with generated data: post articles from random
+    /// users, spread them over groups and cast votes, choosing articles
+    /// with Zipf distribution. Store rate limits are applied as usual,
+    /// so they should be disabled for large workloads.
+    pub async fn seed(
+        &self,
+        config: &SeedConfig,
+    ) -> Result<SeedReport, FeedError> {
+        let config = Arc::new(config.clone());
+        // Links differ between runs, so duplicate detection doesn't
+        // return articles of the previous run.
+        let run = get_sys_time_in_secs();
+        let mut phases = Vec::new();
+
+        let ids = Arc::new(Mutex::new(Vec::with_capacity(config.articles)));
+        let (store, cfg, posted) = (self.clone(), config.clone(), ids.clone());
+        phases.push(
+            run_phase("post", config.articles, config.concurrency, move |i| {
+                let (store, cfg, posted) =
+                    (store.clone(), cfg.clone(), posted.clone());
+                async move {
+                    let mut rng = Rng::for_op(cfg.seed, 0, i);
+                    let user = format!("seed-user-{}", rng.below(cfg.users));
+                    let id = store
+                        .post_article(
+                            &user,
+                            &format!("Seed article {i}"),
+                            &format!("https://example.com/seed/{run}/{i}"),
+                        )
+                        .await?;
+                    posted.lock().unwrap().push(id);
+                    Ok(())
+                }
+            })
+            .await?,
+        );
+
+        // Popularity rank follows posting order
+        let ids = {
+            let mut ids = ids.lock().unwrap().clone();
+            ids.sort();
+            Arc::new(ids)
+        };
+
+        if config.groups > 0 {
+            let (store, cfg, articles) =
+                (self.clone(), config.clone(), ids.clone());
+            phases.push(
+                run_phase("group", ids.len(), config.concurrency, move |i| {
+                    let (store, cfg, articles) =
+                        (store.clone(), cfg.clone(), articles.clone());
+                    async move {
+                        let group = format!("seed-{}", i % cfg.groups);
+                        store
+                            .add_remove_groups(articles[i], &[&group], &[])
+                            .await?;
+                        Ok(())
+                    }
+                })
+                .await?,
+            );
+        }
+
+        if !ids.is_empty() {
+            let zipf = Arc::new(Zipf::new(ids.len(), config.zipf_exponent));
+            let (store, cfg, articles) =
+                (self.clone(), config.clone(), ids.clone());
+            phases.push(
+                run_phase("vote", config.votes, config.concurrency, move |i| {
+                    let (store, cfg, articles, zipf) = (
+                        store.clone(),
+                        cfg.clone(),
+                        articles.clone(),
+                        zipf.clone(),
+                    );
+                    async move {
+                        let mut rng = Rng::for_op(cfg.seed, 1, i);
+                        let article = articles[zipf.sample(&mut rng)];
+                        let user =
+                            format!("seed-user-{}", rng.below(cfg.users));
+                        let upvote = rng.next_f64() < cfg.upvote_ratio;
+                        store.article_vote(&user, article, upvote).await
+                    }
+                })
+                .await?,
+            );
+        }
+
+        Ok(SeedReport { phases })
+    }
+}
+
+// ───── Helpers ──────────────────────────────────────────────────────────── //
+
+/// Run `ops` operations `op(0..ops)` on `concurrency` workers,
+/// measuring latency of each one.
+async fn run_phase<F, Fut>(
+    name: &'static str,
+    ops: usize,
+    concurrency: usize,
+    op: F,
+) -> Result<PhaseReport, FeedError>
+where
+    F: Fn(usize) -> Fut + Send + Sync + 'static,
+    Fut: Future<Output = Result<(), FeedError>> + Send + 'static,
+{
+    let op = Arc::new(op);
+    let next = Arc::new(AtomicUsize::new(0));
+    let started = Instant::now();
+
+    let workers = (0..concurrency.min(ops))
+        .map(|_| {
+            let (op, next) = (op.clone(), next.clone());
+            tokio::spawn(async move {
+                let mut latencies = Vec::new();
+                loop {
+                    let i = next.fetch_add(1, Ordering::Relaxed);
+                    if i >= ops {
+                        return Ok::<_, FeedError>(latencies);
+                    }
+                    let op_started = Instant::now();
+                    op(i).await?;
+                    latencies.push(op_started.elapsed());
+                }
+            })
+        })
+        .collect::<Vec<_>>();
+
+    let mut latencies = Vec::with_capacity(ops);
+    for worker in workers {
+        latencies.extend(worker.await.expect("Seed worker panicked")?);
+    }
+    Ok(PhaseReport::new(name, started.elapsed(), latencies))
+}
+
+/// Value below which `p` share of sorted `latencies` fall.
+fn percentile(latencies: &[Duration], p: f64) -> Duration {
+    if latencies.is_empty() {
+        return Duration::ZERO;
+    }
+    let rank = (p * latencies.len() as f64).ceil() as usize;
+    latencies[rank.clamp(1, latencies.len()) - 1]
+}
+
+/// Small xorshift generator, good enough for workload generation.
+struct Rng(u64);
+
+impl Rng {
+    /// Independent generator for operation `op` of the `phase`.
+    fn for_op(seed: u64, phase: u64, op: usize) -> Self {
+        let mut state = seed
+            ^ phase.wrapping_mul(0xD1B5_4A32_D192_ED03)
+            ^ (op as u64).wrapping_mul(0x9E37_79B9_7F4A_7C15);
+        // Zero state is a fixed point of xorshift
+        if state == 0 {
+            state = 0x2545_F491_4F6C_DD1D;
+        }
+        let mut rng = Rng(state);
+        rng.next_u64();
+        rng
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        self.0
+    }
+
+    /// Uniform value in `[0, 1)`.
+    fn next_f64(&mut self) -> f64 {
+        (self.next_u64() >> 11) as f64 / (1u64 << 53) as f64
+    }
+
+    /// Uniform value in `0..n`.
+    fn below(&mut self, n: usize) -> usize {
+        (self.next_f64() * n as f64) as usize
+    }
+}
+
+/// Zipf distribution over ranks `0..n`, rank `k` has weight
+/// `1 / (k + 1) ^ exponent`.
+struct Zipf {
+    cdf: Vec<f64>,
+}
+
+impl Zipf {
+    fn new(n: usize, exponent: f64) -> Self {
+        let mut total = 0.;
+        let mut cdf = (1..=n)
+            .map(|k| {
+                total += 1. / (k as f64).powf(exponent);
+                total
+            })
+            .collect::<Vec<_>>();
+        for value in cdf.iter_mut() {
+            *value /= total;
+        }
+        Zipf { cdf }
+    }
+
+    fn sample(&self, rng: &mut Rng) -> usize {
+        let x = rng.next_f64();
+        self.cdf
+            .partition_point(|&p| p <= x)
+            .min(self.cdf.len() - 1)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn zipf_prefers_top_ranks() {
+        let zipf = Zipf::new(100, 1.0);
+        let mut rng = Rng::for_op(7, 0, 0);
+        let mut counts = [0; 100];
+        for _ in 0..100_000 {
+            counts[zipf.sample(&mut rng)] += 1;
+        }
+        // Weight of rank 0 is twice the weight of rank 1, and 1 / H(100)
+        // of the total, which is about 19%.
+        assert!((17_000..21_000).contains(&counts[0]), "{}", counts[0]);
+        assert!(counts[0] > counts[1] * 3 / 2);
+        assert!(counts[1] > counts[50]);
+    }
+
+    #[test]
+    fn zero_exponent_is_uniform() {
+        let zipf = Zipf::new(4, 0.);
+        let mut rng = Rng::for_op(7, 1, 0);
+        let mut counts = [0; 4];
+        for _ in 0..40_000 {
+            counts[zipf.sample(&mut rng)] += 1;
+        }
+        assert!(counts.iter().all(|&c| (9_000..11_000).contains(&c)));
+    }
+
+    #[test]
+    fn percentiles_use_nearest_rank() {
+        let latencies =
+            (1..=100).map(Duration::from_millis).collect::<Vec<_>>();
+        assert_eq!(percentile(&latencies, 0.5), Duration::from_millis(50));
+        assert_eq!(percentile(&latencies, 0.99), Duration::from_millis(99));
+        assert_eq!(percentile(&latencies, 1.0), Duration::from_millis(100));
+        assert_eq!(percentile(&[], 0.5), Duration::ZERO);
+    }
+
+    #[test]
+    fn options_override_defaults() {
+        let config =
+            SeedConfig::from_args(&["--users", "5", "--zipf", "1.2"]).unwrap();
+        assert_eq!(config.users, 5);
+        assert_eq!(config.zipf_exponent, 1.2);
+        assert_eq!(config.articles, SeedConfig::default().articles);
+        assert!(SeedConfig::from_args(&["--users"]).is_err());
+        assert!(SeedConfig::from_args(&["--color", "red"]).is_err());
+    }
+}
@@ -0,0 +1,108 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use fred::clients::RedisClient;
+
+use crate::follows::FollowWeights;
+use crate::rate_limit::RateLimits;
+use crate::scoring::{LinearDecay, ScoringPolicy};
+use crate::ONE_WEEK_IN_SECONDS;
+
+/// Entry point of the feed library.
+///
+/// Wraps a connected `RedisClient` and exposes all article operations
+/// (posting, voting, grouping, listing) as methods. Methods are defined
+/// in the module responsible for the corresponding feature.
+#[derive(Clone)]
+pub struct ArticleStore {
+    pub(crate) client: RedisClient,
+    pub(crate) scoring: Arc<dyn ScoringPolicy>,
+    pub(crate) rate_limits: RateLimits,
+    /// Reposting a link within this window returns the existing article.
+    pub(crate) duplicate_window: Duration,
+    /// Votes received during this window make the `trending:` zset.
+    pub(crate) trending_window: Duration,
+    /// Authors can edit their articles during this time after posting.
+    pub(crate) edit_window: Duration,
+    /// Boost of followed groups and authors on personalized front pages.
+    pub(crate) follow_weights: FollowWeights,
+    /// IP address of the caller, used for per-IP rate limits.
+    pub(crate) ip: Option<Arc<str>>,
+}
+
+impl ArticleStore {
+    /// Create a new store on top of an already initialized client,
+    /// with `LinearDecay` scoring, without rate limits, with one week
+    /// duplicate link window, one hour trending window, two hours
+    /// edit window and default follow weights.
+    pub fn new(client: RedisClient) -> Self {
+        ArticleStore {
+            client,
+            scoring: Arc::new(LinearDecay::default()),
+            rate_limits: RateLimits::default(),
+            duplicate_window: Duration::from_secs(ONE_WEEK_IN_SECONDS as u64),
+            trending_window: Duration::from_secs(60 * 60),
+            edit_window: Duration::from_secs(2 * 60 * 60),
+            follow_weights: FollowWeights::default(),
+            ip: None,
+        }
+    }
+
+    /// Replace scoring policy. Call `recompute_scores` after changing
+    /// policy of existing data.
+    pub fn with_scoring<P: ScoringPolicy + 'static>(
+        mut self,
+        policy: P,
+    ) -> Self {
+        self.scoring = Arc::new(policy);
+        self
+    }
+
+    /// Replace rate limits applied to posting and voting.
+    pub fn with_rate_limits(mut self, rate_limits: RateLimits) -> Self {
+        self.rate_limits = rate_limits;
+        self
+    }
+
+    /// Replace window in which posting an already posted link returns
+    /// the existing article. Zero window disables duplicate detection.
+    pub fn with_duplicate_window(mut self, window: Duration) -> Self {
+        self.duplicate_window = window;
+        self
+    }
+
+    /// Replace window of votes counted by `refresh_trending`, it is
+    /// rounded up to whole minutes.
+    pub fn with_trending_window(mut self, window: Duration) -> Self {
+        self.trending_window = window;
+        self
+    }
+
+    /// Replace time after posting during which author can edit
+    /// the article.
+    pub fn with_edit_window(mut self, window: Duration) -> Self {
+        self.edit_window = window;
+        self
+    }
+
+    /// Replace boost of followed groups and authors, it should match
+    /// the scale of the scoring policy.
+    pub fn with_follow_weights(mut self, weights: FollowWeights) -> Self {
+        self.follow_weights = weights;
+        self
+    }
+
+    /// Copy of the store acting on behalf of the caller with given IP,
+    /// so per-IP rate limits are applied. Cheap, can be called per request.
+    pub fn with_ip(&self, ip: &str) -> Self {
+        ArticleStore {
+            ip: Some(ip.into()),
+            ..self.clone()
+        }
+    }
+
+    /// Underlying redis client.
+    pub fn client(&self) -> &RedisClient {
+        &self.client
+    }
+}
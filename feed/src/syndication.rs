@@ -0,0 +1,304 @@
+use std::fmt::Write;
+
+use fred::error::RedisError;
+use time::format_description::well_known::{Rfc2822, Rfc3339};
+use time::formatting::Formattable;
+use time::OffsetDateTime;
+
+use crate::listing::ArticleOrder;
+use crate::{Article, ArticleStore};
+
+/// Syndication document format.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FeedFormat {
+    /// RSS 2.0, `application/rss+xml`.
+    Rss,
+    /// Atom 1.0, `application/atom+xml`.
+    Atom,
+}
+
+impl FeedFormat {
+    /// Name of the format in feed URLs, `rss` or `atom`.
+    pub fn name(&self) -> &'static str {
+        match self {
+            FeedFormat::Rss => "rss",
+            FeedFormat::Atom => "atom",
+        }
+    }
+
+    pub fn content_type(&self) -> &'static str {
+        match self {
+            FeedFormat::Rss => "application/rss+xml; charset=utf-8",
+            FeedFormat::Atom => "application/atom+xml; charset=utf-8",
+        }
+    }
+}
+
+/// Listing published as a feed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FeedSource<'a> {
+    /// Front page, `score:` zset.
+    Top,
+    /// Newest articles, `time:` zset.
+    Newest,
+    /// Best articles of the `group:{name}` set.
+    Group(&'a str),
+}
+
+impl FeedSource<'_> {
+    fn title(&self) -> String {
+        match self {
+            FeedSource::Top => "Top articles".to_string(),
+            FeedSource::Newest => "Newest articles".to_string(),
+            FeedSource::Group(group) => format!("Top articles in {group}"),
+        }
+    }
+
+    /// Path of the feed on the site, like `/feeds/atom/top`, the same
+    /// as in the HTTP API.
+    pub fn path(&self, format: FeedFormat) -> String {
+        match self {
+            FeedSource::Top => format!("/feeds/{}/top", format.name()),
+            FeedSource::Newest => format!("/feeds/{}/newest", format.name()),
+            FeedSource::Group(group) => {
+                format!("/feeds/{}/groups/{group}", format.name())
+            }
+        }
+    }
+}
+
+impl ArticleStore {
+    /// Render first `limit` articles of the listing as RSS or Atom
+    /// document. `site` is the public URL of the feed site, it is used
+    /// for links to the feed itself and for entry ids.
+    pub async fn export_feed(
+        &self,
+        source: FeedSource<'_>,
+        format: FeedFormat,
+        site: &str,
+        limit: usize,
+    ) -> Result<String, RedisError> {
+        let page = match source {
+            FeedSource::Top => {
+                self.get_articles_page(ArticleOrder::Score, limit, None)
+                    .await?
+            }
+            FeedSource::Newest => {
+                self.get_articles_page(ArticleOrder::Time, limit, None)
+                    .await?
+            }
+            FeedSource::Group(group) => {
+                self.get_group_articles_page(
+                    group,
+                    ArticleOrder::Score,
+                    limit,
+                    None,
+                )
+                .await?
+            }
+        };
+
+        let title = source.title();
+        Ok(match format {
+            FeedFormat::Rss => render_rss(&title, site, &page.articles),
+            FeedFormat::Atom => {
+                render_atom(&title, site, &source.path(format), &page.articles)
+            }
+        })
+    }
+}
+
+/// RSS 2.0 channel with one item per article.
+pub fn render_rss(title: &str, site: &str, articles: &[Article]) -> String {
+    let mut xml = String::new();
+    xml.push_str(r#"<?xml version="1.0" encoding="UTF-8"?>"#);
+    xml.push('\n');
+    xml.push_str(
+        r#"<rss version="2.0" xmlns:dc="http://purl.org/dc/elements/1.1/">"#,
+    );
+    xml.push_str("\n<channel>\n");
+    let _ = writeln!(xml, "<title>{}</title>", escape(title));
+    let _ = writeln!(xml, "<link>{}</link>", escape(site));
+    let _ = writeln!(xml, "<description>{}</description>", escape(title));
+    if let Some(time) = articles.iter().map(|a| a.time).max() {
+        let _ =
+            writeln!(xml, "<lastBuildDate>{}</lastBuildDate>", rfc822(time));
+    }
+    for article in articles.iter() {
+        xml.push_str("<item>\n");
+        let _ = writeln!(xml, "<title>{}</title>", escape(&article.title));
+        let _ = writeln!(xml, "<link>{}</link>", escape(&article.link));
+        let _ = writeln!(
+            xml,
+            r#"<guid isPermaLink="false">{}</guid>"#,
+            escape(&Article::key(article.id))
+        );
+        let _ = writeln!(xml, "<pubDate>{}</pubDate>", rfc822(article.time));
+        let _ = writeln!(
+            xml,
+            "<dc:creator>{}</dc:creator>",
+            escape(&article.author)
+        );
+        xml.push_str("</item>\n");
+    }
+    xml.push_str("</channel>\n</rss>\n");
+    xml
+}
+
+/// Atom 1.0 feed with one entry per article, published at `path` of the
+/// site. Feed id is its own URL, so every listing has its own id, entry ids
+/// are `{site}/articles/{article_id}`, so they stay the same between
+/// exports.
+pub fn render_atom(
+    title: &str,
+    site: &str,
+    path: &str,
+    articles: &[Article],
+) -> String {
+    let site = site.trim_end_matches('/');
+    let url = format!("{site}{path}");
+    let updated = articles.iter().map(|a| a.time).max().unwrap_or(0);
+
+    let mut xml = String::new();
+    xml.push_str(r#"<?xml version="1.0" encoding="UTF-8"?>"#);
+    xml.push('\n');
+    xml.push_str(r#"<feed xmlns="http://www.w3.org/2005/Atom">"#);
+    xml.push('\n');
+    let _ = writeln!(xml, "<id>{}</id>", escape(&url));
+    let _ = writeln!(xml, "<title>{}</title>", escape(title));
+    let _ = writeln!(xml, r#"<link href="{}/"/>"#, escape(site));
+    let _ = writeln!(xml, r#"<link rel="self" href="{}"/>"#, escape(&url));
+    let _ = writeln!(xml, "<updated>{}</updated>", rfc3339(updated));
+    for article in articles.iter() {
+        xml.push_str("<entry>\n");
+        let _ =
+            writeln!(xml, "<id>{}/articles/{}</id>", escape(site), article.id);
+        let _ = writeln!(xml, "<title>{}</title>", escape(&article.title));
+        let _ = writeln!(xml, r#"<link href="{}"/>"#, escape(&article.link));
+        let _ = writeln!(xml, "<updated>{}</updated>", rfc3339(article.time));
+        let _ = writeln!(
+            xml,
+            "<author><name>{}</name></author>",
+            escape(&article.author)
+        );
+        xml.push_str("</entry>\n");
+    }
+    xml.push_str("</feed>\n");
+    xml
+}
+
+// ───── Helpers ──────────────────────────────────────────────────────────── //
+
+/// Escape text for XML element content and attribute values.
+fn escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&apos;"),
+            // Control characters are not allowed in XML 1.0
+            c if c.is_control() && !matches!(c, '\t' | '\n' | '\r') => {}
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+/// Date and time of the unix timestamp, formatted in UTC.
+fn format_time(time: u64, format: &impl Formattable) -> String {
+    OffsetDateTime::from_unix_timestamp(time.min(i64::MAX as u64) as i64)
+        .unwrap_or(OffsetDateTime::UNIX_EPOCH)
+        .format(format)
+        .unwrap_or_default()
+}
+
+/// RFC 2822 date used by RSS, like `Tue, 04 Jun 2024 17:05:00 +0000`.
+fn rfc822(time: u64) -> String {
+    format_time(time, &Rfc2822)
+}
+
+/// RFC 3339 date used by Atom, like `2024-06-04T17:05:00Z`.
+fn rfc3339(time: u64) -> String {
+    format_time(time, &Rfc3339)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn article(title: &str, link: &str) -> Article {
+        Article {
+            id: 7,
+            title: title.to_string(),
+            link: link.to_string(),
+            author: "ghashy".to_string(),
+            time: 1_717_520_700,
+            upvotes: 1,
+            downvotes: 0,
+            comments: 0,
+            score: None,
+            hidden: None,
+            edited: None,
+        }
+    }
+
+    #[test]
+    fn dates_are_formatted_in_utc() {
+        assert_eq!(rfc822(0), "Thu, 01 Jan 1970 00:00:00 +0000");
+        assert_eq!(rfc3339(0), "1970-01-01T00:00:00Z");
+        assert_eq!(rfc822(1_717_520_700), "Tue, 04 Jun 2024 17:05:00 +0000");
+        assert_eq!(rfc3339(1_717_520_700), "2024-06-04T17:05:00Z");
+        // Leap day
+        assert_eq!(rfc3339(951_782_400), "2000-02-29T00:00:00Z");
+        assert_eq!(rfc822(951_955_199), "Wed, 01 Mar 2000 23:59:59 +0000");
+    }
+
+    #[test]
+    fn titles_and_links_are_escaped() {
+        let articles =
+            [article("Cats & <dogs>", "https://example.com/?a=1&b=\"2\"")];
+        let rss = render_rss("Top", "https://feed.example", &articles);
+        let atom = render_atom(
+            "Top",
+            "https://feed.example/",
+            "/feeds/atom/top",
+            &articles,
+        );
+        for xml in [&rss, &atom] {
+            assert!(xml.contains("<title>Cats &amp; &lt;dogs&gt;</title>"));
+            assert!(
+                xml.contains("https://example.com/?a=1&amp;b=&quot;2&quot;")
+            );
+            assert!(!xml.contains("<dogs>"));
+        }
+        assert!(
+            rss.contains("<pubDate>Tue, 04 Jun 2024 17:05:00 +0000</pubDate>")
+        );
+        assert!(atom.contains("<id>https://feed.example/articles/7</id>"));
+        assert!(atom.contains("<updated>2024-06-04T17:05:00Z</updated>"));
+    }
+
+    #[test]
+    fn atom_feeds_have_own_ids() {
+        let atom = |source: FeedSource| {
+            render_atom(
+                "Top",
+                "https://feed.example",
+                &source.path(FeedFormat::Atom),
+                &[],
+            )
+        };
+        let top = atom(FeedSource::Top);
+        assert!(top.contains("<id>https://feed.example/feeds/atom/top</id>"));
+        assert!(top.contains(
+            r#"<link rel="self" href="https://feed.example/feeds/atom/top"/>"#
+        ));
+        let rust = atom(FeedSource::Group("rust"));
+        assert!(rust
+            .contains("<id>https://feed.example/feeds/atom/groups/rust</id>"));
+        assert!(!atom(FeedSource::Newest).contains("/top</id>"));
+    }
+}
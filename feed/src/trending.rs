@@ -0,0 +1,225 @@
+use std::time::Duration;
+
+use fred::error::RedisError;
+use fred::interfaces::{
+    HashesInterface, KeysInterface, LuaInterface, SortedSetsInterface,
+};
+
+use crate::listing::{Cursor, Page};
+use crate::{get_sys_time_in_secs, Article, ArticleStore};
+
+/// Length of one vote counter bucket, in seconds.
+const BUCKET_SECONDS: u64 = 60;
+
+/// Hash of per-minute vote counters of the article,
+/// `votes:{article_id}`, fields are minutes since the unix epoch.
+pub(crate) fn velocity_key(article_id: u32) -> String {
+    format!("votes:{article_id}")
+}
+
+impl ArticleStore {
+    /// Add vote balance change `delta` to the current bucket of the
+    /// article, and mark article as recently voted in the `votes:` zset.
+    pub(crate) async fn record_velocity(
+        &self,
+        article_id: u32,
+        delta: i64,
+    ) -> Result<(), RedisError> {
+        let now = get_sys_time_in_secs();
+        let key = velocity_key(article_id);
+        let window = self.trending_buckets() * BUCKET_SECONDS;
+
+        let pipe = self.client.pipeline();
+        pipe.hincrby::<(), _, _>(&key, now / BUCKET_SECONDS, delta)
+            .await?;
+        pipe.expire::<(), _>(&key, (window + BUCKET_SECONDS) as i64)
+            .await?;
+        pipe.zadd::<(), _, _>(
+            "votes:",
+            None,
+            None,
+            false,
+            false,
+            vec![(now as f64, Article::key(article_id))],
+        )
+        .await?;
+        pipe.all().await
+    }
+
+    /// Rebuild the `trending:` zset from vote counters of the last
+    /// trending window. Article score is the sum of its vote balance
+    /// changes during the window, only articles with positive sum are
+    /// included. Returns the number of trending articles.
+    pub async fn refresh_trending(&self) -> Result<usize, RedisError> {
+        let now_bucket = get_sys_time_in_secs() / BUCKET_SECONDS;
+        let first_bucket = now_bucket + 1 - self.trending_buckets();
+        self.client
+            .eval(
+                REFRESH_TRENDING_LUA,
+                vec!["votes:", "trending:"],
+                vec![first_bucket, first_bucket * BUCKET_SECONDS],
+            )
+            .await
+    }
+
+    /// This task should run in background, it refreshes the `trending:`
+    /// zset every `interval`. Errors are logged, and refresh is retried
+    /// after the next interval.
+    pub async fn trending_task(&self, interval: Duration) {
+        loop {
+            if let Err(e) = self.refresh_trending().await {
+                eprintln!("Trending refresh failed: {e}");
+            }
+            tokio::time::sleep(interval).await;
+        }
+    }
+
+    /// Trending articles, the fastest gaining votes first.
+    /// Same as `get_articles_by_score`, but uses the `trending:` zset.
+    pub async fn get_trending_articles(
+        &self,
+        page: i64,
+    ) -> Result<Vec<Article>, RedisError> {
+        self.get_articles_ordered_by_score(page, "trending:").await
+    }
+
+    /// Cursor-based listing of trending articles.
+    pub async fn get_trending_page(
+        &self,
+        page_size: usize,
+        cursor: Option<&Cursor>,
+    ) -> Result<Page, RedisError> {
+        self.get_page("trending:", page_size, cursor).await
+    }
+
+    fn trending_buckets(&self) -> u64 {
+        window_buckets(self.trending_window)
+    }
+}
+
+/// Number of buckets in the trending window, at least one.
+fn window_buckets(window: Duration) -> u64 {
+    window.as_secs().div_ceil(BUCKET_SECONDS).max(1)
+}
+
+/// Rebuild trending zset `KEYS[2]` from the articles in `KEYS[1]` zset,
+/// which were voted after `ARGV[2]` timestamp. For each article its
+/// `votes:{article_id}` buckets starting from `ARGV[1]` are summed,
+/// older buckets are removed.
+const REFRESH_TRENDING_LUA: &str = r#"
+local first = tonumber(ARGV[1])
+redis.call('ZREMRANGEBYSCORE', KEYS[1], '-inf', '(' .. ARGV[2])
+redis.call('DEL', KEYS[2])
+local trending = 0
+for _, article in ipairs(redis.call('ZRANGE', KEYS[1], 0, -1)) do
+    local id = string.match(article, '^article:(%d+)$')
+    if id then
+        local buckets = 'votes:' .. id
+        local counters = redis.call('HGETALL', buckets)
+        local sum = 0
+        for i = 1, #counters, 2 do
+            if tonumber(counters[i]) < first then
+                redis.call('HDEL', buckets, counters[i])
+            else
+                sum = sum + tonumber(counters[i + 1])
+            end
+        end
+        if sum > 0 then
+            redis.call('ZADD', KEYS[2], sum, article)
+            trending = trending + 1
+        end
+    end
+end
+return trending
+"#;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::init_redis_client;
+
+    #[test]
+    fn window_is_rounded_up_to_whole_buckets() {
+        let buckets = |secs| window_buckets(Duration::from_secs(secs));
+        assert_eq!(buckets(0), 1);
+        assert_eq!(buckets(60), 1);
+        assert_eq!(buckets(61), 2);
+        assert_eq!(buckets(60 * 60), 60);
+    }
+
+    #[tokio::test]
+    async fn only_recent_positive_votes_are_trending() {
+        let store = ArticleStore::new(init_redis_client().await)
+            .with_trending_window(Duration::from_secs(10 * BUCKET_SECONDS));
+        let client = store.client();
+        let mut ids = Vec::new();
+        for i in 0..4 {
+            let id = store
+                .post_article(
+                    &format!("trending-author-{i}"),
+                    "trending",
+                    &crate::unique_link("trending.com"),
+                )
+                .await
+                .unwrap();
+            ids.push(id);
+        }
+
+        // Buckets of each article, relative to the current one.
+        // Window is 10 buckets, so -8 stays in it if the minute changes.
+        let now = get_sys_time_in_secs();
+        let now_bucket = now / BUCKET_SECONDS;
+        let buckets: [&[(u64, i64)]; 4] = [
+            // Old bucket is pruned and doesn't count
+            &[(20, 10), (0, 2)],
+            // Negative sum is not trending
+            &[(0, -3), (1, 1)],
+            // Votes at the start of the window count
+            &[(8, 1)],
+            // Voted before the window, dropped from `votes:`
+            &[(20, 5)],
+        ];
+        for (id, buckets) in ids.iter().zip(buckets) {
+            let key = velocity_key(*id);
+            client.del::<(), _>(&key).await.unwrap();
+            for (ago, delta) in buckets {
+                client
+                    .hset::<(), _, _>(&key, (now_bucket - ago, *delta))
+                    .await
+                    .unwrap();
+            }
+            let last = buckets.iter().map(|(ago, _)| ago).min().unwrap();
+            client
+                .zadd::<(), _, _>(
+                    "votes:",
+                    None,
+                    None,
+                    false,
+                    false,
+                    ((now - last * BUCKET_SECONDS) as f64, Article::key(*id)),
+                )
+                .await
+                .unwrap();
+        }
+
+        store.refresh_trending().await.unwrap();
+
+        let scores = |id: u32| async move {
+            let trending: Option<f64> =
+                client.zscore("trending:", Article::key(id)).await.unwrap();
+            let voted: Option<f64> =
+                client.zscore("votes:", Article::key(id)).await.unwrap();
+            (trending, voted.is_some())
+        };
+        assert_eq!(scores(ids[0]).await, (Some(2.), true));
+        assert_eq!(scores(ids[1]).await, (None, true));
+        assert_eq!(scores(ids[2]).await, (Some(1.), true));
+        assert_eq!(scores(ids[3]).await, (None, false));
+
+        let pruned: bool = client
+            .hexists(velocity_key(ids[0]), now_bucket - 20)
+            .await
+            .unwrap();
+        assert!(!pruned);
+    }
+}
@@ -0,0 +1,234 @@
+use std::collections::HashMap;
+
+use fred::error::RedisError;
+use fred::interfaces::{HashesInterface, SortedSetsInterface};
+
+use crate::article::parse_optional_field;
+use crate::listing::{Cursor, Page};
+use crate::voting::voted_key;
+use crate::ArticleStore;
+
+/// User profile, as it is stored in the `user:{name}` hash.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UserProfile {
+    pub name: String,
+    /// Balance of votes for all articles and comments of the user,
+    /// not counting the initial upvote of the author.
+    pub karma: i64,
+    /// Unix timestamp of the first post, `None` if user only voted.
+    pub created: Option<u64>,
+}
+
+impl UserProfile {
+    /// Key of the user hash, `user:{name}`.
+    pub fn key(name: &str) -> String {
+        format!("user:{name}")
+    }
+
+    fn from_hash(
+        name: &str,
+        mut hash: HashMap<String, String>,
+    ) -> Result<Self, RedisError> {
+        Ok(UserProfile {
+            name: name.to_string(),
+            karma: parse_optional_field(&mut hash, "karma")?.unwrap_or(0),
+            created: parse_optional_field(&mut hash, "created")?,
+        })
+    }
+}
+
+/// Zset of articles posted by the user, scored by post time.
+pub(crate) fn submitted_key(user: &str) -> String {
+    format!("submitted:{user}")
+}
+
+impl ArticleStore {
+    /// Fetch user profile, `None` if user never posted anything
+    /// and nobody voted for the user's posts.
+    pub async fn get_user(
+        &self,
+        name: &str,
+    ) -> Result<Option<UserProfile>, RedisError> {
+        let hash: HashMap<String, String> =
+            self.client.hgetall(UserProfile::key(name)).await?;
+        if hash.is_empty() {
+            return Ok(None);
+        }
+        UserProfile::from_hash(name, hash).map(Some)
+    }
+
+    /// Cursor-based listing of articles posted by the user, the newest
+    /// first. Uses the `submitted:{name}` zset.
+    pub async fn get_user_submissions(
+        &self,
+        name: &str,
+        page_size: usize,
+        cursor: Option<&Cursor>,
+    ) -> Result<Page, RedisError> {
+        self.get_page(&submitted_key(name), page_size, cursor).await
+    }
+
+    /// Cursor-based listing of articles upvoted by the user, the most
+    /// recently upvoted first. Uses the `voted:{name}` zset.
+    pub async fn get_user_upvoted(
+        &self,
+        name: &str,
+        page_size: usize,
+        cursor: Option<&Cursor>,
+    ) -> Result<Page, RedisError> {
+        self.get_page(&voted_key(name), page_size, cursor).await
+    }
+
+    /// Add article to the `submitted:{user}` zset, and create
+    /// the `user:{user}` profile if it doesn't exist yet.
+    pub(crate) async fn record_submission(
+        &self,
+        user: &str,
+        article: &str,
+        now: u64,
+    ) -> Result<(), RedisError> {
+        let profile = UserProfile::key(user);
+        let pipe = self.client.pipeline();
+        pipe.zadd::<(), _, _>(
+            submitted_key(user),
+            None,
+            None,
+            false,
+            false,
+            vec![(now as f64, article)],
+        )
+        .await?;
+        pipe.hsetnx::<(), _, _, _>(&profile, "created", now).await?;
+        pipe.hsetnx::<(), _, _, _>(&profile, "karma", 0).await?;
+        pipe.all::<()>().await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::init_redis_client;
+
+    /// User name which was never used before, so counters and listings
+    /// of earlier test runs don't leak into the test.
+    async fn unique_user(store: &ArticleStore, prefix: &str) -> String {
+        let fixture = store
+            .post_article(
+                "users-fixture",
+                "users",
+                &crate::unique_link("users.com"),
+            )
+            .await
+            .unwrap();
+        format!("{prefix}{fixture}")
+    }
+
+    async fn karma(store: &ArticleStore, user: &str) -> i64 {
+        store.get_user(user).await.unwrap().unwrap().karma
+    }
+
+    fn ids(page: &Page) -> Vec<u32> {
+        page.articles.iter().map(|article| article.id).collect()
+    }
+
+    #[tokio::test]
+    async fn votes_change_author_karma() {
+        let store = ArticleStore::new(init_redis_client().await);
+        let author = unique_user(&store, "karma-author").await;
+        let article_id = store
+            .post_article(&author, "karma", &crate::unique_link("karma.com"))
+            .await
+            .unwrap();
+        let profile = store.get_user(&author).await.unwrap().unwrap();
+        assert_eq!(profile.karma, 0);
+        assert!(profile.created.is_some());
+
+        store
+            .article_vote("karma-voter", article_id, true)
+            .await
+            .unwrap();
+        assert_eq!(karma(&store, &author).await, 1);
+        store
+            .article_vote("karma-critic", article_id, false)
+            .await
+            .unwrap();
+        assert_eq!(karma(&store, &author).await, 0);
+        store.unvote("karma-critic", article_id).await.unwrap();
+        assert_eq!(karma(&store, &author).await, 1);
+
+        let comment_id = store
+            .post_comment(&author, article_id, None, "karma")
+            .await
+            .unwrap();
+        // Author's own upvote of the comment doesn't count
+        assert_eq!(karma(&store, &author).await, 1);
+        store
+            .comment_vote("karma-voter", comment_id, true)
+            .await
+            .unwrap();
+        assert_eq!(karma(&store, &author).await, 2);
+        store
+            .comment_vote("karma-voter", comment_id, false)
+            .await
+            .unwrap();
+        assert_eq!(karma(&store, &author).await, 0);
+        store
+            .comment_unvote("karma-voter", comment_id)
+            .await
+            .unwrap();
+        assert_eq!(karma(&store, &author).await, 1);
+    }
+
+    #[tokio::test]
+    async fn submissions_and_upvoted_articles_are_listed() {
+        let store = ArticleStore::new(init_redis_client().await);
+        let author = unique_user(&store, "submitter").await;
+        let voter = format!("{author}-voter");
+        let mut posted = Vec::new();
+        for _ in 0..2 {
+            let article_id = store
+                .post_article(
+                    &author,
+                    "submitted",
+                    &crate::unique_link("submitted.com"),
+                )
+                .await
+                .unwrap();
+            posted.push(article_id);
+        }
+
+        let page = store.get_user_submissions(&author, 10, None).await.unwrap();
+        let mut submitted = ids(&page);
+        submitted.sort();
+        assert_eq!(submitted, posted);
+        assert_eq!(page.next, None);
+
+        store.article_vote(&voter, posted[0], true).await.unwrap();
+        store.article_vote(&voter, posted[1], false).await.unwrap();
+        let page = store.get_user_upvoted(&voter, 10, None).await.unwrap();
+        assert_eq!(ids(&page), vec![posted[0]]);
+
+        // Changed and retracted upvotes leave the listing
+        store.article_vote(&voter, posted[1], true).await.unwrap();
+        store.article_vote(&voter, posted[0], false).await.unwrap();
+        let page = store.get_user_upvoted(&voter, 10, None).await.unwrap();
+        assert_eq!(ids(&page), vec![posted[1]]);
+        store.unvote(&voter, posted[1]).await.unwrap();
+        let page = store.get_user_upvoted(&voter, 10, None).await.unwrap();
+        assert!(page.articles.is_empty());
+    }
+
+    #[test]
+    fn profile_without_karma_field_has_zero_karma() {
+        let hash = HashMap::from([("created".to_string(), "17".to_string())]);
+        let profile = UserProfile::from_hash("ghashy", hash).unwrap();
+        assert_eq!(
+            profile,
+            UserProfile {
+                name: "ghashy".to_string(),
+                karma: 0,
+                created: Some(17),
+            }
+        );
+    }
+}
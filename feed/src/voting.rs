@@ -0,0 +1,338 @@
+use fred::error::RedisError;
+use fred::interfaces::LuaInterface;
+
+use crate::rate_limit::Action;
+use crate::scoring::Scored;
+use crate::{
+    get_sys_time_in_secs, Article, ArticleStore, FeedError, ONE_WEEK_IN_SECONDS,
+};
+
+/// Keys of the item users vote for: article or comment.
+pub(crate) struct VoteTarget {
+    /// Hash with `time`, `upvotes` and `downvotes` fields.
+    pub(crate) hash: String,
+    pub(crate) upvoted: String,
+    pub(crate) downvoted: String,
+    /// Zset with post time of the item, used to check voting window.
+    pub(crate) time_zset: String,
+    /// Zset where item score is stored.
+    pub(crate) score_zset: String,
+    /// Track upvoted items in the `voted:{user}` zset.
+    pub(crate) track_voted: bool,
+}
+
+impl VoteTarget {
+    pub(crate) fn article(article_id: u32) -> Self {
+        VoteTarget {
+            hash: Article::key(article_id),
+            upvoted: format!("upvoted:{article_id}"),
+            downvoted: format!("downvoted:{article_id}"),
+            time_zset: "time:".to_string(),
+            score_zset: "score:".to_string(),
+            track_voted: true,
+        }
+    }
+}
+
+impl ArticleStore {
+    /// Vote for certain article
+    /// We can add vote if there are no vote for given user
+    /// or change vote from upvote -> downvote or downvote -> upvote.
+    /// Vote state check and update are performed atomically by the
+    /// `VOTE_LUA` script, so concurrent votes can't be double counted.
+    /// Article score is recomputed with the store scoring policy.
+    /// If vote was changed, `FeedEvent::Voted` with the written counters
+    /// and score is published to the global channel and channels of the
+    /// article groups. When votes race, only the last writer publishes.
+    /// Fails with `FeedError::Banned` if user is banned and with
+    /// `FeedError::Throttled` if user votes too often.
+    pub async fn article_vote(
+        &self,
+        user: &str,
+        article_id: u32,
+        is_upvote: bool,
+    ) -> Result<(), FeedError> {
+        self.ensure_not_banned(user).await?;
+        self.check_rate_limit(Action::Vote, user).await?;
+        let target = VoteTarget::article(article_id);
+        let (delta, scored) = self.vote(user, &target, is_upvote).await?;
+        if delta != 0 {
+            self.record_velocity(article_id, delta).await?;
+        }
+        if let Some(scored) = scored {
+            self.publish_voted(article_id, scored).await?;
+        }
+        Ok(())
+    }
+
+    /// Retract user's vote for the article, if any.
+    /// Vote set and counters are updated atomically, then article score
+    /// is recomputed, and `FeedEvent::Voted` is published.
+    pub async fn unvote(
+        &self,
+        user: &str,
+        article_id: u32,
+    ) -> Result<(), RedisError> {
+        let target = VoteTarget::article(article_id);
+        let (delta, scored) = self.retract_vote(user, &target).await?;
+        if delta != 0 {
+            self.record_velocity(article_id, delta).await?;
+        }
+        if let Some(scored) = scored {
+            self.publish_voted(article_id, scored).await?;
+        }
+        Ok(())
+    }
+
+    /// Add or toggle vote for the target, if it was posted less than
+    /// a week ago, and recompute its score. Karma of the target author
+    /// is updated in the same script.
+    /// Returns change of the vote balance, 0 if vote state wasn't changed,
+    /// and the result of `update_score`.
+    pub(crate) async fn vote(
+        &self,
+        user: &str,
+        target: &VoteTarget,
+        is_upvote: bool,
+    ) -> Result<(i64, Option<Scored>), RedisError> {
+        let now = get_sys_time_in_secs();
+        let week_ago = now - ONE_WEEK_IN_SECONDS as u64;
+
+        let mut keys = vec![
+            target.hash.clone(),
+            target.upvoted.clone(),
+            target.downvoted.clone(),
+            target.time_zset.clone(),
+        ];
+        if target.track_voted {
+            keys.push(voted_key(user));
+        }
+        let delta: i64 = self
+            .client
+            .eval(
+                VOTE_LUA,
+                keys,
+                vec![
+                    user.to_string(),
+                    (is_upvote as u8).to_string(),
+                    week_ago.to_string(),
+                    now.to_string(),
+                ],
+            )
+            .await?;
+        if delta == 0 {
+            return Ok((0, None));
+        }
+        let scored =
+            self.update_score(&target.hash, &target.score_zset).await?;
+        Ok((delta, scored))
+    }
+
+    /// Remove user's vote for the target, if any, and recompute its score.
+    /// Returns change of the vote balance, 0 if there was no vote,
+    /// and the result of `update_score`.
+    pub(crate) async fn retract_vote(
+        &self,
+        user: &str,
+        target: &VoteTarget,
+    ) -> Result<(i64, Option<Scored>), RedisError> {
+        let mut keys = vec![
+            target.hash.clone(),
+            target.upvoted.clone(),
+            target.downvoted.clone(),
+        ];
+        if target.track_voted {
+            keys.push(voted_key(user));
+        }
+        let delta: i64 = self.client.eval(UNVOTE_LUA, keys, user).await?;
+        if delta == 0 {
+            return Ok((0, None));
+        }
+        let scored =
+            self.update_score(&target.hash, &target.score_zset).await?;
+        Ok((delta, scored))
+    }
+}
+
+/// Zset of articles upvoted by the user, scored by vote time.
+pub(crate) fn voted_key(user: &str) -> String {
+    format!("voted:{user}")
+}
+
+/// Add vote of user `ARGV[1]` for item `KEYS[1]`, `ARGV[2]` is `1` for
+/// upvote and `0` for downvote. `KEYS[2]` and `KEYS[3]` are upvoted and
+/// downvoted sets of the item, `KEYS[4]` is zset with item post time.
+/// Item should be posted after `ARGV[3]` timestamp, otherwise
+/// vote is ignored.
+/// Karma of the item author in the `user:{name}` hash is changed by the
+/// same delta as the vote balance. If `KEYS[5]` is given, it is the
+/// `voted:{user}` zset, where upvoted item is stored with `ARGV[4]` time.
+/// Returns change of the vote balance (from -2 to 2), 0 if nothing changed.
+const VOTE_LUA: &str = r#"
+local time = redis.call('ZSCORE', KEYS[4], KEYS[1])
+if not time or tonumber(time) < tonumber(ARGV[3]) then
+    return 0
+end
+local to, from, to_field, from_field, sign
+if ARGV[2] == '1' then
+    to, from, to_field, from_field = KEYS[2], KEYS[3], 'upvotes', 'downvotes'
+    sign = 1
+else
+    to, from, to_field, from_field = KEYS[3], KEYS[2], 'downvotes', 'upvotes'
+    sign = -1
+end
+if redis.call('SISMEMBER', to, ARGV[1]) == 1 then
+    return 0
+end
+local delta = sign
+if redis.call('SMOVE', from, to, ARGV[1]) == 1 then
+    redis.call('HINCRBY', KEYS[1], from_field, -1)
+    delta = sign * 2
+else
+    redis.call('SADD', to, ARGV[1])
+end
+redis.call('HINCRBY', KEYS[1], to_field, 1)
+local author = redis.call('HGET', KEYS[1], 'author')
+if author then
+    redis.call('HINCRBY', 'user:' .. author, 'karma', delta)
+end
+if KEYS[5] then
+    if sign == 1 then
+        redis.call('ZADD', KEYS[5], ARGV[4], KEYS[1])
+    else
+        redis.call('ZREM', KEYS[5], KEYS[1])
+    end
+end
+return delta
+"#;
+
+/// Remove user `ARGV[1]` from vote sets `KEYS[2]` and `KEYS[3]` and
+/// decrement the corresponding counter of item `KEYS[1]`, updating karma
+/// of the author and `voted:{user}` zset `KEYS[4]`, if given.
+/// Returns change of the vote balance: -1 if upvote was removed, 1 for
+/// downvote and 0 if there was no vote.
+const UNVOTE_LUA: &str = r#"
+local delta = 0
+if redis.call('SREM', KEYS[2], ARGV[1]) == 1 then
+    redis.call('HINCRBY', KEYS[1], 'upvotes', -1)
+    if KEYS[4] then
+        redis.call('ZREM', KEYS[4], KEYS[1])
+    end
+    delta = -1
+elseif redis.call('SREM', KEYS[3], ARGV[1]) == 1 then
+    redis.call('HINCRBY', KEYS[1], 'downvotes', -1)
+    delta = 1
+else
+    return 0
+end
+local author = redis.call('HGET', KEYS[1], 'author')
+if author then
+    redis.call('HINCRBY', 'user:' .. author, 'karma', delta)
+end
+return delta
+"#;
+
+#[cfg(test)]
+mod tests {
+    use fred::interfaces::{
+        HashesInterface, SetsInterface, SortedSetsInterface,
+    };
+
+    use super::*;
+    use crate::init_redis_client;
+    use crate::scoring::{LinearDecay, ScoringPolicy};
+
+    async fn assert_consistent(store: &ArticleStore, article_id: u32) {
+        let client = store.client();
+        let article = Article::key(article_id);
+        let (time, upvotes, downvotes): (u64, i64, i64) = client
+            .hmget(&article, vec!["time", "upvotes", "downvotes"])
+            .await
+            .unwrap();
+        let upvoted: i64 =
+            client.scard(format!("upvoted:{article_id}")).await.unwrap();
+        let downvoted: i64 = client
+            .scard(format!("downvoted:{article_id}"))
+            .await
+            .unwrap();
+        assert_eq!(upvotes, upvoted);
+        assert_eq!(downvotes, downvoted);
+
+        let score: f64 = client.zscore("score:", &article).await.unwrap();
+        let expected =
+            LinearDecay::default().score(time, upvotes, downvotes, 0);
+        assert_eq!(score, expected);
+    }
+
+    #[tokio::test]
+    async fn parallel_votes_from_one_user_are_counted_once() {
+        let store = ArticleStore::new(init_redis_client().await);
+        let article_id = store
+            .post_article(
+                "author",
+                "parallel votes",
+                &crate::unique_link("votes.com"),
+            )
+            .await
+            .unwrap();
+
+        let tasks = (0..300)
+            .map(|_| {
+                let store = store.clone();
+                tokio::spawn(async move {
+                    store.article_vote("voter", article_id, true).await
+                })
+            })
+            .collect::<Vec<_>>();
+        for task in tasks {
+            task.await.unwrap().unwrap();
+        }
+
+        let article = store.get_article(article_id).await.unwrap().unwrap();
+        // Author's vote and a single vote from "voter"
+        assert_eq!(article.upvotes, 2);
+        assert_eq!(article.downvotes, 0);
+        assert_consistent(&store, article_id).await;
+    }
+
+    #[tokio::test]
+    async fn parallel_toggles_keep_counters_consistent() {
+        let store = ArticleStore::new(init_redis_client().await);
+        let article_id = store
+            .post_article(
+                "author",
+                "parallel toggles",
+                &crate::unique_link("toggles.com"),
+            )
+            .await
+            .unwrap();
+
+        let tasks = (0..300)
+            .map(|i| {
+                let store = store.clone();
+                tokio::spawn(async move {
+                    match i % 3 {
+                        0 => {
+                            store.article_vote("voter", article_id, true).await
+                        }
+                        1 => {
+                            store.article_vote("voter", article_id, false).await
+                        }
+                        _ => store
+                            .unvote("voter", article_id)
+                            .await
+                            .map_err(FeedError::from),
+                    }
+                })
+            })
+            .collect::<Vec<_>>();
+        for task in tasks {
+            task.await.unwrap().unwrap();
+        }
+
+        let article = store.get_article(article_id).await.unwrap().unwrap();
+        // "voter" has at most one vote at any moment
+        assert!(article.upvotes + article.downvotes <= 2);
+        assert_consistent(&store, article_id).await;
+    }
+}
@@ -0,0 +1,498 @@
+//! Integration tests of the HTTP API, they need redis-server
+//! reachable by `init_redis_client`.
+
+use std::net::SocketAddr;
+use std::time::Duration;
+
+use axum::body::Body;
+use axum::extract::ConnectInfo;
+use axum::http::{header, Request, StatusCode};
+use axum::Router;
+use http_body_util::BodyExt;
+use serde_json::{json, Value};
+use tower::ServiceExt;
+
+use feed::api::router;
+use feed::{
+    get_sys_time_in_secs, init_redis_client, ArticleStore, RateLimit,
+    RateLimits,
+};
+
+async fn app() -> Router {
+    router(ArticleStore::new(init_redis_client().await))
+}
+
+/// Name unique for the test run, so tests don't see each other's data.
+fn unique(name: &str) -> String {
+    let nanos = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap()
+        .subsec_nanos();
+    format!("{name}-{}-{nanos}", get_sys_time_in_secs())
+}
+
+async fn send(
+    app: &Router,
+    method: &str,
+    uri: &str,
+    body: Option<Value>,
+) -> (StatusCode, Value) {
+    let request = Request::builder().method(method).uri(uri);
+    let request = match body {
+        Some(body) => request
+            .header(header::CONTENT_TYPE, "application/json")
+            .body(Body::from(body.to_string())),
+        None => request.body(Body::empty()),
+    }
+    .unwrap();
+
+    let response = app.clone().oneshot(request).await.unwrap();
+    let status = response.status();
+    let bytes = response.into_body().collect().await.unwrap().to_bytes();
+    let json = if bytes.is_empty() {
+        Value::Null
+    } else {
+        serde_json::from_slice(&bytes).unwrap()
+    };
+    (status, json)
+}
+
+async fn post_article(app: &Router, user: &str) -> u64 {
+    let (status, body) = send(
+        app,
+        "POST",
+        "/articles",
+        Some(json!({
+            "user": user,
+            "title": "Kittens",
+            "link": format!("https://example.com/{}", unique("kittens")),
+        })),
+    )
+    .await;
+    assert_eq!(status, StatusCode::CREATED);
+    body["id"].as_u64().unwrap()
+}
+
+#[tokio::test]
+async fn posted_article_can_be_voted_and_listed() {
+    let app = app().await;
+    let author = unique("author");
+    let id = post_article(&app, &author).await;
+
+    let (status, article) =
+        send(&app, "GET", &format!("/articles/{id}"), None).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(article["author"], author.as_str());
+    assert_eq!(article["upvotes"], 1);
+
+    let vote = json!({ "user": unique("voter"), "upvote": false });
+    let (status, _) = send(
+        &app,
+        "POST",
+        &format!("/articles/{id}/vote"),
+        Some(vote.clone()),
+    )
+    .await;
+    assert_eq!(status, StatusCode::NO_CONTENT);
+    let (_, article) =
+        send(&app, "GET", &format!("/articles/{id}"), None).await;
+    assert_eq!(article["downvotes"], 1);
+
+    let (status, _) =
+        send(&app, "POST", &format!("/articles/{id}/unvote"), Some(vote)).await;
+    assert_eq!(status, StatusCode::NO_CONTENT);
+    let (_, article) =
+        send(&app, "GET", &format!("/articles/{id}"), None).await;
+    assert_eq!(article["downvotes"], 0);
+
+    // Newest article is on top of the time listing
+    let (status, page) =
+        send(&app, "GET", "/articles?order=time&page_size=100", None).await;
+    assert_eq!(status, StatusCode::OK);
+    let ids = page["articles"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|article| article["id"].as_u64().unwrap())
+        .collect::<Vec<_>>();
+    assert!(ids.contains(&id));
+}
+
+#[tokio::test]
+async fn group_listing_follows_group_changes() {
+    let app = app().await;
+    let group = unique("group");
+    let id = post_article(&app, &unique("author")).await;
+    let uri = format!("/groups/{group}/articles?order=time");
+
+    let groups = json!({ "add": [group] });
+    let (status, _) = send(
+        &app,
+        "POST",
+        &format!("/articles/{id}/groups"),
+        Some(groups),
+    )
+    .await;
+    assert_eq!(status, StatusCode::NO_CONTENT);
+    let (_, page) = send(&app, "GET", &uri, None).await;
+    assert_eq!(page["articles"][0]["id"], id);
+    assert_eq!(page["next"], Value::Null);
+
+    let groups = json!({ "remove": [group] });
+    send(
+        &app,
+        "POST",
+        &format!("/articles/{id}/groups"),
+        Some(groups),
+    )
+    .await;
+    let (_, page) = send(&app, "GET", &uri, None).await;
+    assert_eq!(page["articles"], json!([]));
+}
+
+#[tokio::test]
+async fn listing_pages_are_linked_with_cursor() {
+    let app = app().await;
+    let group = unique("group");
+    let mut posted = Vec::new();
+    for _ in 0..3 {
+        let id = post_article(&app, &unique("author")).await;
+        let groups = json!({ "add": [group] });
+        send(
+            &app,
+            "POST",
+            &format!("/articles/{id}/groups"),
+            Some(groups),
+        )
+        .await;
+        posted.push(id);
+    }
+
+    let mut seen = Vec::new();
+    let mut uri = format!("/groups/{group}/articles?page_size=2");
+    loop {
+        let (status, page) = send(&app, "GET", &uri, None).await;
+        assert_eq!(status, StatusCode::OK);
+        for article in page["articles"].as_array().unwrap() {
+            seen.push(article["id"].as_u64().unwrap());
+        }
+        match page["next"].as_str() {
+            Some(cursor) => {
+                uri = format!(
+                    "/groups/{group}/articles?page_size=2&cursor={cursor}"
+                )
+            }
+            None => break,
+        }
+    }
+    seen.sort();
+    assert_eq!(seen, posted);
+}
+
+#[tokio::test]
+async fn missing_article_is_not_found() {
+    let app = app().await;
+    let uri = format!("/articles/{}", u32::MAX);
+    let (status, body) = send(&app, "GET", &uri, None).await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+    assert!(body["error"].is_string());
+
+    let vote = json!({ "user": "nobody", "upvote": true });
+    let (status, _) =
+        send(&app, "POST", &format!("{uri}/vote"), Some(vote)).await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+async fn invalid_cursor_is_bad_request() {
+    let app = app().await;
+    let (status, _) =
+        send(&app, "GET", "/articles?cursor=not-a-cursor", None).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+}
+
+#[tokio::test]
+async fn invalid_group_name_is_bad_request() {
+    let app = app().await;
+    let id = post_article(&app, &unique("author")).await;
+    for group in ["", "front:alice"] {
+        let (status, _) = send(
+            &app,
+            "POST",
+            &format!("/articles/{id}/groups"),
+            Some(json!({ "add": [group] })),
+        )
+        .await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+    }
+    let (status, _) =
+        send(&app, "GET", "/groups/front:alice/articles", None).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+}
+
+#[tokio::test]
+async fn throttled_post_is_too_many_requests() {
+    let store = ArticleStore::new(init_redis_client().await).with_rate_limits(
+        RateLimits {
+            post_per_user: Some(RateLimit::new(1, Duration::from_secs(60))),
+            ..Default::default()
+        },
+    );
+    let app = router(store);
+    let user = unique("spammer");
+    post_article(&app, &user).await;
+
+    let (status, _) = send(
+        &app,
+        "POST",
+        "/articles",
+        Some(json!({ "user": user, "title": "Again", "link": "example.com" })),
+    )
+    .await;
+    assert_eq!(status, StatusCode::TOO_MANY_REQUESTS);
+}
+
+#[tokio::test]
+async fn posts_from_one_ip_are_throttled() {
+    let store = ArticleStore::new(init_redis_client().await).with_rate_limits(
+        RateLimits {
+            post_per_ip: Some(RateLimit::new(1, Duration::from_secs(60))),
+            ..Default::default()
+        },
+    );
+    let app = router(store);
+    // IP unique for the test run, so earlier runs don't fill its window
+    let seed = get_sys_time_in_secs() as u32 ^ std::process::id();
+    let [_, a, b, c] = seed.to_be_bytes();
+    let addr = SocketAddr::from(([10, a, b, c], 40000));
+
+    let mut statuses = Vec::new();
+    for _ in 0..2 {
+        let body = json!({
+            "user": unique("author"),
+            "title": "Kittens",
+            "link": format!("https://example.com/{}", unique("kittens")),
+        });
+        let mut request = Request::builder()
+            .method("POST")
+            .uri("/articles")
+            .header(header::CONTENT_TYPE, "application/json")
+            .body(Body::from(body.to_string()))
+            .unwrap();
+        request.extensions_mut().insert(ConnectInfo(addr));
+        let response = app.clone().oneshot(request).await.unwrap();
+        statuses.push(response.status());
+    }
+    assert_eq!(
+        statuses,
+        [StatusCode::CREATED, StatusCode::TOO_MANY_REQUESTS]
+    );
+}
+
+#[tokio::test]
+async fn duplicate_post_returns_existing_article() {
+    let app = app().await;
+    let post = json!({
+        "user": unique("author"),
+        "title": "Kittens",
+        "link": format!("https://example.com/{}", unique("kittens")),
+    });
+    let (status, first) =
+        send(&app, "POST", "/articles", Some(post.clone())).await;
+    assert_eq!(status, StatusCode::CREATED);
+    let (status, second) = send(&app, "POST", "/articles", Some(post)).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(first["id"], second["id"]);
+}
+
+#[tokio::test]
+async fn group_feeds_are_exported() {
+    let app = app().await;
+    let group = unique("group");
+    let id = post_article(&app, &unique("author")).await;
+    let groups = json!({ "add": [group] });
+    send(
+        &app,
+        "POST",
+        &format!("/articles/{id}/groups"),
+        Some(groups),
+    )
+    .await;
+
+    for (format, content_type) in [
+        ("rss", "application/rss+xml"),
+        ("atom", "application/atom+xml"),
+    ] {
+        let request = Request::builder()
+            .uri(format!("/feeds/{format}/groups/{group}"))
+            .header(header::HOST, "feed.example")
+            .body(Body::empty())
+            .unwrap();
+        let response = app.clone().oneshot(request).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let content = response.headers()[header::CONTENT_TYPE].clone();
+        assert!(content.to_str().unwrap().starts_with(content_type));
+        let bytes = response.into_body().collect().await.unwrap().to_bytes();
+        let xml = String::from_utf8(bytes.to_vec()).unwrap();
+        assert!(xml.contains("<title>Kittens</title>"), "{xml}");
+    }
+
+    let (status, _) =
+        send(&app, "GET", &format!("/feeds/json/groups/{group}"), None).await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+async fn banned_user_is_forbidden() {
+    let store = ArticleStore::new(init_redis_client().await);
+    let app = router(store.clone());
+    let user = unique("troll");
+    let id = post_article(&app, &unique("author")).await;
+    store.ban_user(&user).await.unwrap();
+
+    let (status, _) = send(
+        &app,
+        "POST",
+        "/articles",
+        Some(json!({ "user": user, "title": "Spam", "link": "spam.com" })),
+    )
+    .await;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+    let vote = json!({ "user": user, "upvote": false });
+    let (status, _) =
+        send(&app, "POST", &format!("/articles/{id}/vote"), Some(vote)).await;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+
+    store.unban_user(&user).await.unwrap();
+}
+
+#[tokio::test]
+async fn author_edits_are_kept_in_revisions() {
+    let app = app().await;
+    let author = unique("author");
+    let id = post_article(&app, &author).await;
+    let (_, original) =
+        send(&app, "GET", &format!("/articles/{id}"), None).await;
+
+    let edit = json!({
+        "user": unique("stranger"),
+        "title": "Dogs",
+        "link": "https://example.com/dogs",
+    });
+    let (status, _) =
+        send(&app, "POST", &format!("/articles/{id}/edit"), Some(edit)).await;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+
+    let edit = json!({
+        "user": author,
+        "title": "Dogs",
+        "link": format!("https://example.com/{}", unique("dogs")),
+    });
+    let (status, _) =
+        send(&app, "POST", &format!("/articles/{id}/edit"), Some(edit)).await;
+    assert_eq!(status, StatusCode::NO_CONTENT);
+
+    let (_, article) =
+        send(&app, "GET", &format!("/articles/{id}"), None).await;
+    assert_eq!(article["title"], "Dogs");
+    assert!(article["edited"].is_u64());
+    let (status, revisions) =
+        send(&app, "GET", &format!("/articles/{id}/revisions"), None).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(revisions[0]["title"], original["title"]);
+    assert_eq!(revisions[0]["link"], original["link"]);
+}
+
+#[tokio::test]
+async fn edit_cant_take_link_of_recent_article() {
+    let app = app().await;
+    let author = unique("author");
+    let id = post_article(&app, &author).await;
+    let other = post_article(&app, &unique("author")).await;
+    let (_, other_article) =
+        send(&app, "GET", &format!("/articles/{other}"), None).await;
+
+    let edit = json!({
+        "user": author,
+        "title": "Kittens",
+        "link": other_article["link"],
+    });
+    let (status, _) =
+        send(&app, "POST", &format!("/articles/{id}/edit"), Some(edit)).await;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+
+    // Reposting the link still finds the other article
+    let (status, body) = send(
+        &app,
+        "POST",
+        "/articles",
+        Some(json!({
+            "user": unique("author"),
+            "title": "Kittens",
+            "link": other_article["link"],
+        })),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body["id"], other);
+}
+
+#[tokio::test]
+async fn articles_are_found_by_title_words() {
+    let app = app().await;
+    let word = unique("kitten").replace('-', "");
+    let (_, posted) = send(
+        &app,
+        "POST",
+        "/articles",
+        Some(json!({
+            "user": unique("author"),
+            "title": format!("The {word} and the dog"),
+            "link": format!("https://example.com/{word}"),
+        })),
+    )
+    .await;
+    let id = posted["id"].as_u64().unwrap();
+
+    let (status, page) =
+        send(&app, "GET", &format!("/search?q={word}+dog"), None).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(page["articles"][0]["id"], id);
+
+    let (_, page) =
+        send(&app, "GET", &format!("/search?q={word}+-dog"), None).await;
+    assert_eq!(page["articles"], json!([]));
+}
+
+#[tokio::test]
+async fn followed_authors_are_boosted_on_front_page() {
+    let app = app().await;
+    let author = unique("author");
+    let reader = unique("reader");
+    let followed = post_article(&app, &author).await;
+    let other = post_article(&app, &unique("author")).await;
+
+    let (status, _) = send(
+        &app,
+        "POST",
+        &format!("/users/{reader}/follows"),
+        Some(json!({ "follow": { "authors": [author] } })),
+    )
+    .await;
+    assert_eq!(status, StatusCode::NO_CONTENT);
+    let (_, follows) =
+        send(&app, "GET", &format!("/users/{reader}/follows"), None).await;
+    assert_eq!(follows, json!({ "groups": [], "authors": [author] }));
+
+    let (status, page) = send(
+        &app,
+        "GET",
+        &format!("/users/{reader}/front?order=time"),
+        None,
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+    // Newer article of the other author is outranked
+    assert_eq!(page["articles"][0]["id"], followed);
+    assert_ne!(followed, other);
+}